@@ -0,0 +1,707 @@
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkCodec;
+use crate::ihdr::Ihdr;
+use crate::png::Png;
+
+const ANIMATION_CONTROL_LENGTH: usize = 8;
+const FRAME_CONTROL_LENGTH: usize = 26;
+const SEQUENCE_NUMBER_LENGTH: usize = 4;
+
+/// Chunk types carrying sequence numbers, which cannot be added without renumbering.
+pub const APNG_CHUNK_TYPES: [&str; 3] = [
+    AnimationControl::CHUNK_TYPE,
+    FrameControl::CHUNK_TYPE,
+    FrameData::CHUNK_TYPE,
+];
+
+/// The acTL chunk, which marks a PNG as animated.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct AnimationControl {
+    num_frames: u32,
+    num_plays: u32,
+}
+
+impl AnimationControl {
+    pub const CHUNK_TYPE: &'static str = "acTL";
+
+    pub fn num_frames(&self) -> u32 {
+        self.num_frames
+    }
+
+    /// How many times the animation plays, 0 meaning forever.
+    pub fn num_plays(&self) -> u32 {
+        self.num_plays
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        [self.num_frames.to_be_bytes(), self.num_plays.to_be_bytes()].concat()
+    }
+}
+
+impl TryFrom<&[u8]> for AnimationControl {
+    type Error = ApngError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != ANIMATION_CONTROL_LENGTH {
+            return Err(ApngError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        Ok(AnimationControl {
+            num_frames: read_u32(data, 0),
+            num_plays: read_u32(data, 4),
+        })
+    }
+}
+
+impl Display for AnimationControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.num_plays {
+            0 => write!(f, "{} frames, looping forever", self.num_frames),
+            plays => write!(f, "{} frames, played {} times", self.num_frames, plays),
+        }
+    }
+}
+
+/// What happens to a frame's region before the next frame is rendered.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum DisposeOp {
+    None,
+    Background,
+    Previous,
+}
+
+/// How a frame is combined with the output buffer.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum BlendOp {
+    Source,
+    Over,
+}
+
+impl Display for DisposeOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Background => write!(f, "background"),
+            Self::Previous => write!(f, "previous"),
+        }
+    }
+}
+
+impl Display for BlendOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Source => write!(f, "source"),
+            Self::Over => write!(f, "over"),
+        }
+    }
+}
+
+/// The fcTL chunk, giving the region, timing and compositing of one frame.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct FrameControl {
+    sequence_number: u32,
+    width: u32,
+    height: u32,
+    x_offset: u32,
+    y_offset: u32,
+    delay_num: u16,
+    delay_den: u16,
+    dispose_op: DisposeOp,
+    blend_op: BlendOp,
+}
+
+impl FrameControl {
+    pub const CHUNK_TYPE: &'static str = "fcTL";
+
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn x_offset(&self) -> u32 {
+        self.x_offset
+    }
+
+    pub fn y_offset(&self) -> u32 {
+        self.y_offset
+    }
+
+    /// The frame delay in seconds, where a denominator of 0 means hundredths.
+    pub fn delay(&self) -> f64 {
+        let denominator = match self.delay_den {
+            0 => 100,
+            den => den,
+        };
+        self.delay_num as f64 / denominator as f64
+    }
+
+    pub fn dispose_op(&self) -> DisposeOp {
+        self.dispose_op
+    }
+
+    pub fn blend_op(&self) -> BlendOp {
+        self.blend_op
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(FRAME_CONTROL_LENGTH);
+        for value in [
+            self.sequence_number,
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+        ] {
+            bytes.extend(value.to_be_bytes());
+        }
+        bytes.extend(self.delay_num.to_be_bytes());
+        bytes.extend(self.delay_den.to_be_bytes());
+        bytes.push(self.dispose_op as u8);
+        bytes.push(self.blend_op as u8);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for FrameControl {
+    type Error = ApngError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != FRAME_CONTROL_LENGTH {
+            return Err(ApngError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        let width = read_u32(data, 4);
+        let height = read_u32(data, 8);
+        if width == 0 || height == 0 {
+            return Err(ApngError::EmptyFrame(read_u32(data, 0)));
+        }
+
+        let dispose_op = match data[24] {
+            0 => DisposeOp::None,
+            1 => DisposeOp::Background,
+            2 => DisposeOp::Previous,
+            op => return Err(ApngError::InvalidDisposeOp(op)),
+        };
+        let blend_op = match data[25] {
+            0 => BlendOp::Source,
+            1 => BlendOp::Over,
+            op => return Err(ApngError::InvalidBlendOp(op)),
+        };
+
+        Ok(FrameControl {
+            sequence_number: read_u32(data, 0),
+            width,
+            height,
+            x_offset: read_u32(data, 12),
+            y_offset: read_u32(data, 16),
+            delay_num: u16::from_be_bytes([data[20], data[21]]),
+            delay_den: u16::from_be_bytes([data[22], data[23]]),
+            dispose_op,
+            blend_op,
+        })
+    }
+}
+
+impl Display for FrameControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {}), {:.3} s, dispose {}, blend {}",
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+            self.delay(),
+            self.dispose_op,
+            self.blend_op
+        )
+    }
+}
+
+/// The fdAT chunk, holding part of a frame's compressed image data.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FrameData {
+    sequence_number: u32,
+    data: Vec<u8>,
+}
+
+impl FrameData {
+    pub const CHUNK_TYPE: &'static str = "fdAT";
+
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    /// The image data, laid out like the data of an IDAT chunk.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        [&self.sequence_number.to_be_bytes()[..], &self.data].concat()
+    }
+}
+
+impl TryFrom<&[u8]> for FrameData {
+    type Error = ApngError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < SEQUENCE_NUMBER_LENGTH {
+            return Err(ApngError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        Ok(FrameData {
+            sequence_number: read_u32(data, 0),
+            data: data[SEQUENCE_NUMBER_LENGTH..].to_vec(),
+        })
+    }
+}
+
+impl Display for FrameData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sequence {}, {} bytes of image data",
+            self.sequence_number,
+            self.data.len()
+        )
+    }
+}
+
+/// One frame of an animation, as indices into the chunks of the PNG.
+pub struct Frame {
+    control: FrameControl,
+    control_index: usize,
+    data_indices: Vec<usize>,
+    end: usize,
+    default_image: bool,
+}
+
+impl Frame {
+    pub fn control(&self) -> &FrameControl {
+        &self.control
+    }
+
+    /// Indices of the IDAT or fdAT chunks holding the frame's image data.
+    pub fn data_indices(&self) -> &[usize] {
+        &self.data_indices
+    }
+
+    /// The chunks from this frame's fcTL up to, not including, the next frame's fcTL.
+    pub fn span(&self) -> std::ops::Range<usize> {
+        self.control_index..self.end
+    }
+
+    /// Where a chunk belonging to this frame can go without splitting its image data.
+    pub fn insert_position(&self) -> usize {
+        self.data_indices.last().map_or(self.control_index, |&i| i) + 1
+    }
+
+    /// Whether the frame is the IDAT image shown by decoders without APNG support.
+    pub fn is_default_image(&self) -> bool {
+        self.default_image
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.control)?;
+        if self.default_image {
+            write!(f, " (default image)")?;
+        }
+        Ok(())
+    }
+}
+
+/// An animated PNG, checked against the APNG ordering and sequence number rules.
+pub struct Apng<'a> {
+    png: &'a Png,
+    control: AnimationControl,
+    frames: Vec<Frame>,
+}
+
+impl<'a> Apng<'a> {
+    pub fn png(&self) -> &'a Png {
+        self.png
+    }
+
+    pub fn control(&self) -> &AnimationControl {
+        &self.control
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn frame(&self, index: usize) -> Result<&Frame, ApngError> {
+        self.frames
+            .get(index)
+            .ok_or(ApngError::NoSuchFrame(index, self.frames.len()))
+    }
+
+    /// The frame's region as a standalone PNG, without compositing it over earlier frames.
+    ///
+    /// Ancillary chunks from before the image data, such as PLTE, tRNS and colour space
+    /// chunks, are kept; the animation chunks are dropped and fdAT chunks become IDAT.
+    pub fn frame_png(&self, index: usize) -> crate::Result<Png> {
+        let frame = self.frame(index)?;
+        let ihdr = self
+            .png
+            .ihdr()?
+            .with_size(frame.control.width, frame.control.height);
+        let first_data = self
+            .png
+            .position_of(Png::DATA_CHUNK_TYPE)
+            .unwrap_or(self.png.chunks().len());
+
+        let mut chunks = vec![Chunk::new(chunk_type(Ihdr::CHUNK_TYPE), ihdr.as_bytes())];
+        for chunk in self.png.chunks()[..first_data].iter() {
+            let name = chunk.chunk_type().to_string();
+            if name != Ihdr::CHUNK_TYPE && !APNG_CHUNK_TYPES.contains(&name.as_str()) {
+                chunks.push(chunk.clone());
+            }
+        }
+        for &data_index in frame.data_indices.iter() {
+            let chunk = &self.png.chunks()[data_index];
+            let data = match frame.default_image {
+                true => chunk.data().to_vec(),
+                false => chunk.data()[SEQUENCE_NUMBER_LENGTH..].to_vec(),
+            };
+            chunks.push(Chunk::new(chunk_type(Png::DATA_CHUNK_TYPE), data));
+        }
+        chunks.push(Chunk::new(chunk_type(Png::END_CHUNK_TYPE), vec![]));
+
+        Ok(Png::from_chunks(chunks))
+    }
+}
+
+impl<'a> TryFrom<&'a Png> for Apng<'a> {
+    type Error = ApngError;
+
+    fn try_from(png: &'a Png) -> Result<Self, Self::Error> {
+        let control_index = png
+            .position_of(AnimationControl::CHUNK_TYPE)
+            .ok_or(ApngError::NotAnimated)?;
+        let control = AnimationControl::try_from(png.chunks()[control_index].data())?;
+        let first_data = png.position_of(Png::DATA_CHUNK_TYPE);
+        if first_data.is_some_and(|first_data| control_index > first_data) {
+            return Err(ApngError::ControlAfterData);
+        }
+        let (width, height) = png
+            .ihdr()
+            .map(|ihdr| (ihdr.width(), ihdr.height()))
+            .map_err(|_| ApngError::MissingHeader)?;
+
+        let mut frames: Vec<Frame> = vec![];
+        let mut sequence_number = 0;
+        let mut seen_data = false;
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            match chunk.chunk_type().to_string().as_str() {
+                FrameControl::CHUNK_TYPE => {
+                    let frame_control = FrameControl::try_from(chunk.data())?;
+                    expect_sequence(frame_control.sequence_number, &mut sequence_number)?;
+                    let fits_x = frame_control.x_offset.checked_add(frame_control.width);
+                    let fits_y = frame_control.y_offset.checked_add(frame_control.height);
+                    let fits =
+                        fits_x.is_some_and(|x| x <= width) && fits_y.is_some_and(|y| y <= height);
+                    if !fits {
+                        return Err(ApngError::FrameOutOfBounds(frames.len()));
+                    }
+                    if let Some(previous) = frames.last_mut() {
+                        previous.end = index;
+                    }
+                    frames.push(Frame {
+                        control: frame_control,
+                        control_index: index,
+                        data_indices: vec![],
+                        end: png.chunks().len(),
+                        default_image: !seen_data,
+                    });
+                }
+                Png::DATA_CHUNK_TYPE => {
+                    seen_data = true;
+                    if let Some(frame) = frames.last_mut().filter(|frame| frame.default_image) {
+                        frame.data_indices.push(index);
+                    }
+                }
+                FrameData::CHUNK_TYPE => {
+                    let frame_data = FrameData::try_from(chunk.data())?;
+                    expect_sequence(frame_data.sequence_number, &mut sequence_number)?;
+                    match frames.last_mut().filter(|frame| !frame.default_image) {
+                        Some(frame) => frame.data_indices.push(index),
+                        None => return Err(ApngError::DataWithoutFrame(index)),
+                    }
+                }
+                Png::END_CHUNK_TYPE => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.end = index;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(position) = frames.iter().position(|f| f.data_indices.is_empty()) {
+            return Err(ApngError::FrameWithoutData(position));
+        }
+        if frames.len() != control.num_frames as usize {
+            return Err(ApngError::FrameCountMismatch(
+                control.num_frames,
+                frames.len(),
+            ));
+        }
+
+        Ok(Apng {
+            png,
+            control,
+            frames,
+        })
+    }
+}
+
+fn expect_sequence(found: u32, expected: &mut u32) -> Result<(), ApngError> {
+    if found != *expected {
+        return Err(ApngError::SequenceMismatch(*expected, found));
+    }
+    *expected += 1;
+    Ok(())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn chunk_type(name: &str) -> ChunkType {
+    ChunkType::from_str(name).expect("APNG chunk types are valid")
+}
+
+pub struct AnimationControlCodec;
+
+impl ChunkCodec for AnimationControlCodec {
+    type Value = AnimationControl;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(AnimationControl::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<AnimationControl> {
+        Ok(AnimationControl::try_from(data)?)
+    }
+
+    fn encode(&self, value: &AnimationControl) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+pub struct FrameControlCodec;
+
+impl ChunkCodec for FrameControlCodec {
+    type Value = FrameControl;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(FrameControl::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<FrameControl> {
+        Ok(FrameControl::try_from(data)?)
+    }
+
+    fn encode(&self, value: &FrameControl) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+pub struct FrameDataCodec;
+
+impl ChunkCodec for FrameDataCodec {
+    type Value = FrameData;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(FrameData::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<FrameData> {
+        Ok(FrameData::try_from(data)?)
+    }
+
+    fn encode(&self, value: &FrameData) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+#[derive(Debug)]
+pub enum ApngError {
+    NotAnimated,
+    MissingHeader,
+    ControlAfterData,
+    InvalidLength(&'static str, usize),
+    EmptyFrame(u32),
+    InvalidDisposeOp(u8),
+    InvalidBlendOp(u8),
+    SequenceMismatch(u32, u32),
+    FrameOutOfBounds(usize),
+    DataWithoutFrame(usize),
+    FrameWithoutData(usize),
+    FrameCountMismatch(u32, usize),
+    NoSuchFrame(usize, usize),
+}
+
+impl Display for ApngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnimated => write!(f, "Error: no acTL chunk, the PNG is not animated"),
+            Self::MissingHeader => write!(f, "Error: the APNG has no valid IHDR chunk"),
+            Self::ControlAfterData => write!(f, "Error: acTL must come before the first IDAT"),
+            Self::InvalidLength(chunk_type, length) => {
+                write!(
+                    f,
+                    "Error: {} chunk of invalid length {}",
+                    chunk_type, length
+                )
+            }
+            Self::EmptyFrame(sequence_number) => {
+                write!(
+                    f,
+                    "Error: fcTL {} has a zero width or height",
+                    sequence_number
+                )
+            }
+            Self::InvalidDisposeOp(op) => write!(f, "Error: invalid dispose op {}", op),
+            Self::InvalidBlendOp(op) => write!(f, "Error: invalid blend op {}", op),
+            Self::SequenceMismatch(expected, found) => write!(
+                f,
+                "Error: expected sequence number {} but found {}",
+                expected, found
+            ),
+            Self::FrameOutOfBounds(frame) => {
+                write!(f, "Error: frame {} extends past the image", frame)
+            }
+            Self::DataWithoutFrame(index) => {
+                write!(f, "Error: fdAT chunk {} is not preceded by an fcTL", index)
+            }
+            Self::FrameWithoutData(frame) => write!(f, "Error: frame {} has no image data", frame),
+            Self::FrameCountMismatch(declared, found) => write!(
+                f,
+                "Error: acTL declares {} frames but {} were found",
+                declared, found
+            ),
+            Self::NoSuchFrame(frame, count) => {
+                write!(
+                    f,
+                    "Error: no frame {}, the APNG has {} frames",
+                    frame, count
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApngError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_data::ImageData;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+
+    fn frame_control(sequence_number: u32, size: u32, offset: u32) -> Chunk {
+        let control = FrameControl {
+            sequence_number,
+            width: size,
+            height: size,
+            x_offset: offset,
+            y_offset: offset,
+            delay_num: 1,
+            delay_den: 10,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+        };
+        FrameControlCodec.encode_chunk(&control).unwrap()
+    }
+
+    /// Grayscale rows with filter type 0, deflated.
+    fn image_data(size: usize, value: u8) -> Vec<u8> {
+        let rows: Vec<u8> = (0..size)
+            .flat_map(|_| std::iter::once(0).chain(std::iter::repeat_n(value, size)))
+            .collect();
+        compress_to_vec_zlib(&rows, 6)
+    }
+
+    fn animation(sequence_of_data: u32) -> Png {
+        let header = [0, 0, 0, 2, 0, 0, 0, 2, 8, 0, 0, 0, 0];
+        let control = AnimationControl {
+            num_frames: 2,
+            num_plays: 0,
+        };
+        let frame_data = FrameData {
+            sequence_number: sequence_of_data,
+            data: image_data(1, 7),
+        };
+        Png::from_chunks(vec![
+            Chunk::new(chunk_type("IHDR"), header.to_vec()),
+            AnimationControlCodec.encode_chunk(&control).unwrap(),
+            frame_control(0, 2, 0),
+            Chunk::new(chunk_type("IDAT"), image_data(2, 1)),
+            frame_control(1, 1, 1),
+            FrameDataCodec.encode_chunk(&frame_data).unwrap(),
+            Chunk::new(chunk_type("IEND"), vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_frames() {
+        let png = animation(2);
+        let apng = Apng::try_from(&png).unwrap();
+
+        assert_eq!(apng.control().num_frames(), 2);
+        assert_eq!(apng.frames().len(), 2);
+        assert!(apng.frames()[0].is_default_image());
+        assert_eq!(apng.frames()[0].data_indices(), &[3]);
+        assert_eq!(apng.frames()[1].data_indices(), &[5]);
+        assert_eq!(apng.frames()[1].span(), 4..6);
+        assert_eq!(apng.frames()[1].insert_position(), 6);
+        assert_eq!(apng.frames()[1].control().delay(), 0.1);
+    }
+
+    #[test]
+    fn test_sequence_mismatch() {
+        let png = animation(3);
+        assert!(matches!(
+            Apng::try_from(&png),
+            Err(ApngError::SequenceMismatch(2, 3))
+        ));
+    }
+
+    #[test]
+    fn test_frame_png() {
+        let png = animation(2);
+        let apng = Apng::try_from(&png).unwrap();
+
+        let frame = apng.frame_png(1).unwrap();
+        assert!(frame.validate_structure().is_ok());
+        let pixels = ImageData::try_from(&frame).unwrap();
+        assert_eq!(pixels.ihdr().width(), 1);
+        assert_eq!(pixels.bytes(), &[7]);
+
+        assert!(apng.frame_png(2).is_err());
+    }
+
+    #[test]
+    fn test_not_animated() {
+        let png = Png::from_chunks(vec![Chunk::new(chunk_type("IEND"), vec![])]);
+        assert!(matches!(Apng::try_from(&png), Err(ApngError::NotAnimated)));
+    }
+}
@@ -1,9 +1,12 @@
-use clap::{arg, builder::PossibleValue, command, value_parser, Command, ValueEnum};
+use clap::{arg, value_parser, Arg, ArgAction, ArgMatches, Command, ValueEnum};
+use serde::Deserialize;
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use crate::chunk_type;
+use crate::atomic::DEFAULT_BACKUP_SUFFIX;
+use crate::config::Config;
+use crate::physical::PixelDimensions;
 
 //EXAMPLES of commands for this program
 //
@@ -14,13 +17,105 @@ use crate::chunk_type;
 // pngme remove ./dice.png ruSt
 //
 // pngme print ./dice.png
+//
+// pngme extract ./dice.png iCCP -o ./icc.bin
+//
+// pngme insert ./dice.png ./chunk.bin
+//
+// pngme insert ./dice.png iCCP ./icc.bin
+//
+// pngme transplant ./original.png ./regenerated.png --only tEXt --only ruSt
+//
+// pngme strip ./upload.png --keep gAMA --keep sRGB
+//
+// pngme scan ./suspect.png
+//
+// pngme scan ./uploads --recursive --jobs 8
+//
+// pngme strip "./assets/*.png" --input ./logo.png -o ./published
+//
+// cat ./dice.png | pngme encode - ruSt "piped message" | pngme decode - ruSt
+//
+// echo "message from stdin" | pngme encode ./dice.png ruSt -o -
+//
+// pngme remove ./dice.png ruSt --backup=.orig
+//
+// pngme strip ./shared/logo.png --dry-run
+//
+// pngme diff ./before.png ./after.png --critical-only
+//
+// pngme edit ./dice.png
+//
+// pngme print ./dice.png --hex
+//
+// pngme show ./dice.png iCCP --hex --offset 256 --length 128
+//
+// pngme frames ./spinner.png
+//
+// pngme frames ./spinner.png --frame 3 -o ./frame3.png
+//
+// pngme encode ./spinner.png ruSt "hidden in frame 3" --frame 3
+//
+// pngme inspect ./photo.png
+//
+// pngme profile ./photo.png -o ./photo.icc
+//
+// pngme profile ./photo.png --to-srgb
+//
+// pngme profile ./photo.png --embed ./DisplayP3.icc
+//
+// pngme set ./poster.png --dpi 300 --touch
+//
+// pngme encode ./poster.png ruSt "printed copy" --touch
+//
+// pngme exif ./photo.png
+//
+// pngme exif ./photo.png --remove-gps --orientation 1
+//
+// pngme capacity ./dice.png
+//
+// echo 'chunk-type = "ruSt"' > .pngme.toml && pngme encode ./dice.png -m "no type needed"
+//
+// pngme encode ./dice.png ruSt "before the pixels" --placement before-data
+//
+// pngme run ./poster.png --profile publish
+//
+// pngme encode ./dice.png --type ruSt --message-file ./secret.txt --output ./hidden.png
+//
+// pngme decode ./hidden.png -t ruSt
 
+#[derive(Clone)]
 pub struct Arguments {
     action: Action,
     file_path: PathBuf,
+    inputs: Vec<PathBuf>,
     chunk_type: Option<String>,
     message: Option<String>,
+    message_file: Option<PathBuf>,
     output_path: Option<PathBuf>,
+    framed: bool,
+    only: Vec<String>,
+    keep: Vec<String>,
+    force: bool,
+    strip_trailer: Option<bool>,
+    recursive: bool,
+    fail_fast: bool,
+    jobs: Option<usize>,
+    backup: Option<String>,
+    dry_run: bool,
+    critical_only: bool,
+    hex: Option<bool>,
+    offset: usize,
+    length: Option<usize>,
+    frame: Option<usize>,
+    to_srgb: bool,
+    embed: Option<PathBuf>,
+    dpi: Option<f64>,
+    touch: Option<bool>,
+    remove_gps: bool,
+    orientation: Option<u16>,
+    placement: Option<Placement>,
+    steps: Vec<Action>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -29,6 +124,34 @@ pub enum Action {
     Decode,
     Remove,
     Print,
+    Extract,
+    Insert,
+    Transplant,
+    Strip,
+    Scan,
+    Diff,
+    Edit,
+    Show,
+    Frames,
+    Inspect,
+    Profile,
+    Set,
+    Exif,
+    Capacity,
+    Run,
+}
+
+/// Where encode puts a new chunk when no `--frame` is given.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Placement {
+    /// Last, just before IEND
+    #[default]
+    End,
+    /// First, just after IHDR
+    AfterHeader,
+    /// Just before the first IDAT
+    BeforeData,
 }
 
 impl FromStr for Action {
@@ -44,6 +167,361 @@ impl FromStr for Action {
     }
 }
 
+impl Action {
+    /// The subcommand for this action. Its positionals keep the slots of the original
+    /// `ACTION FILE_PATH CHUNK_TYPE MESSAGE OUTPUT_PATH` form, so older invocations still
+    /// parse; slots an action has no use for are hidden.
+    fn command(self) -> Command {
+        let name = self
+            .to_possible_value()
+            .expect("no action is skipped")
+            .get_name()
+            .to_string();
+        let (about, second, third) = match self {
+            Self::Encode => (
+                "Hide a message in a new chunk",
+                Some(("CHUNK_TYPE", "Chunk type to hide the message in")),
+                Some(("MESSAGE", "Message to hide, read from stdin when omitted")),
+            ),
+            Self::Decode => (
+                "Print the message hidden in a chunk",
+                Some(("CHUNK_TYPE", "Chunk type the message is in")),
+                None,
+            ),
+            Self::Remove => (
+                "Remove the first chunk of a type",
+                Some(("CHUNK_TYPE", "Chunk type to remove")),
+                None,
+            ),
+            Self::Print => ("List the chunks of a PNG", None, None),
+            Self::Extract => (
+                "Write the data of a chunk to a file",
+                Some(("CHUNK_TYPE", "Chunk type to extract")),
+                None,
+            ),
+            Self::Insert => (
+                "Insert a chunk read from a file",
+                Some((
+                    "CHUNK",
+                    "Framed chunk file, or the chunk type when DATA follows",
+                )),
+                Some(("DATA", "File holding the raw chunk data")),
+            ),
+            Self::Transplant => (
+                "Copy ancillary chunks into another PNG",
+                Some(("DESTINATION", "PNG to copy the chunks into")),
+                None,
+            ),
+            Self::Strip => ("Remove ancillary chunks", None, None),
+            Self::Scan => ("Report suspicious chunks and data", None, None),
+            Self::Diff => (
+                "Compare the chunks of two PNGs",
+                Some(("OTHER", "PNG to compare with")),
+                None,
+            ),
+            Self::Edit => ("Edit the chunks interactively", None, None),
+            Self::Show => (
+                "Show the decoded contents of a chunk",
+                Some(("CHUNK_TYPE", "Chunk type to show")),
+                None,
+            ),
+            Self::Frames => ("List or extract APNG frames", None, None),
+            Self::Inspect => ("Describe the image, colour and palette chunks", None, None),
+            Self::Profile => ("Extract, replace or embed the ICC profile", None, None),
+            Self::Set => ("Set the pixel density or modification time", None, None),
+            Self::Exif => ("List or edit the Exif tags", None, None),
+            Self::Capacity => ("Report how much each hiding method can carry", None, None),
+            Self::Run => ("Perform the steps of a config profile", None, None),
+        };
+
+        let command = Command::new(name)
+            .about(about)
+            .arg(
+                arg!(<FILE_PATH>)
+                    .help("Path to the file you want to operate on, - for stdin")
+                    .value_parser(value_parser!(String)),
+            )
+            .arg(slot("CHUNK_TYPE", second))
+            .arg(slot("MESSAGE", third))
+            .arg(
+                arg!([OUTPUT_PATH])
+                    .help("Path for output, same as --output")
+                    .value_parser(value_parser!(String))
+                    .hide(self != Self::Encode),
+            )
+            .arg(
+                arg!(-o --output <OUTPUT>)
+                    .help("Path for output, - for stdout")
+                    .value_parser(value_parser!(String)),
+            );
+
+        let chunk_type = arg!(-t --type <TYPE>)
+            .help("Chunk type, same as CHUNK_TYPE")
+            .value_parser(value_parser!(String));
+        let hex = [
+            arg!(--hex)
+                .help("Dump chunk data as offset, hex and ASCII columns")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no-hex"),
+            arg!(--"no-hex")
+                .help("Do not dump chunk data, even when the config sets hex")
+                .action(ArgAction::SetTrue)
+                .overrides_with("hex"),
+            arg!(--offset <OFFSET>)
+                .help("Start the hex dump at this byte of the chunk data")
+                .value_parser(value_parser!(usize))
+                .default_value("0"),
+            arg!(--length <LENGTH>)
+                .help("Dump at most this many bytes of the chunk data")
+                .value_parser(value_parser!(usize)),
+        ];
+        let frame = |help: &'static str| {
+            arg!(--frame <FRAME>)
+                .help(help)
+                .value_parser(value_parser!(usize))
+        };
+        let force = |help: &'static str| arg!(--force).help(help).action(ArgAction::SetTrue);
+        let only = arg!(--only <TYPE>)
+            .help("Only act on chunks of this type, can be given several times")
+            .value_parser(value_parser!(String))
+            .action(ArgAction::Append);
+
+        match self {
+            Self::Encode => command.args([
+                chunk_type,
+                arg!(-m --message <MESSAGE>)
+                    .help("Message to hide, same as MESSAGE")
+                    .value_parser(value_parser!(String)),
+                arg!(--"message-file" <PATH>)
+                    .help("Hide the contents of this file, - for stdin")
+                    .value_parser(value_parser!(String))
+                    .conflicts_with("message"),
+                arg!(--placement <PLACEMENT>)
+                    .help("Where to put the new chunk")
+                    .value_parser(value_parser!(Placement)),
+                frame("Hide the message in this APNG frame"),
+                force("Allow a critical, public or unsafe-to-copy chunk type"),
+            ]),
+            Self::Decode => {
+                command.args([chunk_type, frame("Look for the message in this APNG frame")])
+            }
+            Self::Remove => command.arg(chunk_type),
+            Self::Print => command.args(hex),
+            Self::Extract => command.args([
+                chunk_type,
+                arg!(--framed)
+                    .help("Dump the whole chunk (length, type, data and CRC) instead of its data")
+                    .action(ArgAction::SetTrue),
+            ]),
+            Self::Transplant => command.args([
+                only,
+                force("Also copy unsafe-to-copy chunks when critical chunks differ"),
+            ]),
+            Self::Strip => command.args([
+                only,
+                arg!(--keep <TYPE>)
+                    .help("Keep ancillary chunks of this type, can be given several times")
+                    .value_parser(value_parser!(String))
+                    .action(ArgAction::Append),
+            ]),
+            Self::Diff => command.arg(
+                arg!(--"critical-only")
+                    .help("Only compare critical chunks, ignoring ancillary ones")
+                    .action(ArgAction::SetTrue),
+            ),
+            Self::Show => command.arg(chunk_type).args(hex),
+            Self::Frames => command.arg(frame("Extract this APNG frame")),
+            Self::Profile => command.args([
+                arg!(--"to-srgb")
+                    .help("Replace the embedded ICC profile with an sRGB declaration")
+                    .action(ArgAction::SetTrue),
+                arg!(--embed <PROFILE>)
+                    .help("Embed this ICC profile in place of an sRGB declaration")
+                    .value_parser(value_parser!(String)),
+                force("Replace a profile that does not look like sRGB"),
+            ]),
+            Self::Exif => command.args([
+                arg!(--"remove-gps")
+                    .help("Remove the GPS tags, keeping the rest of the Exif data")
+                    .action(ArgAction::SetTrue),
+                arg!(--orientation <ORIENTATION>)
+                    .help("Rewrite the Orientation tag, 1 to 8")
+                    .value_parser(value_parser!(u16).range(1..=8)),
+            ]),
+            Self::Insert
+            | Self::Scan
+            | Self::Edit
+            | Self::Inspect
+            | Self::Set
+            | Self::Capacity
+            | Self::Run => command,
+        }
+    }
+
+    /// The positional slots after FILE_PATH this action reads, in order.
+    fn slots(self) -> Vec<String> {
+        self.command()
+            .get_positionals()
+            .filter(|arg| !arg.is_hide_set())
+            .skip(1)
+            .map(|arg| arg.get_id().to_string())
+            .collect()
+    }
+
+    /// What this action needs beyond FILE_PATH, checked once the config has been applied.
+    fn rules(self) -> &'static [Rule] {
+        match self {
+            Self::Encode => &[Rule::ChunkTypeOrMessage],
+            Self::Decode | Self::Remove | Self::Show | Self::Extract => &[Rule::ChunkType],
+            Self::Insert => &[Rule::ChunkFile],
+            Self::Transplant => &[Rule::Destination],
+            Self::Diff => &[Rule::ComparedFile],
+            Self::Set => &[Rule::SomethingToSet],
+            Self::Run => &[Rule::Steps],
+            Self::Print
+            | Self::Strip
+            | Self::Scan
+            | Self::Edit
+            | Self::Frames
+            | Self::Inspect
+            | Self::Profile
+            | Self::Exif
+            | Self::Capacity => &[],
+        }
+    }
+}
+
+/// A positional slot of a subcommand, hidden when the action does not use it.
+fn slot(id: &'static str, usage: Option<(&'static str, &'static str)>) -> Arg {
+    let arg = Arg::new(id).value_parser(value_parser!(String));
+    match usage {
+        Some((value_name, help)) => arg.value_name(value_name).help(help),
+        None => arg.hide(true),
+    }
+}
+
+/// One requirement of a subcommand on its arguments.
+#[derive(Copy, Clone)]
+enum Rule {
+    ChunkType,
+    ChunkTypeOrMessage,
+    ChunkFile,
+    Destination,
+    ComparedFile,
+    SomethingToSet,
+    Steps,
+}
+
+impl Rule {
+    fn check(self, arguments: &Arguments) -> Result<(), ArgsErr> {
+        let has_chunk_type = arguments.chunk_type.is_some();
+        let (satisfied, error) = match self {
+            Self::ChunkType => (has_chunk_type, ArgsErr::MissingChunkType),
+            Self::ChunkTypeOrMessage => (
+                has_chunk_type || arguments.message.is_some() || arguments.message_file.is_some(),
+                ArgsErr::MissingMessageAndChunkType,
+            ),
+            Self::ChunkFile => (has_chunk_type, ArgsErr::MissingChunkFile),
+            Self::Destination => (has_chunk_type, ArgsErr::MissingDestination),
+            Self::ComparedFile => (has_chunk_type, ArgsErr::MissingComparedFile),
+            Self::SomethingToSet => (
+                arguments.dpi.is_some() || arguments.touch(),
+                ArgsErr::NothingToSet,
+            ),
+            Self::Steps => (!arguments.steps.is_empty(), ArgsErr::NoSteps),
+        };
+        if satisfied {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+}
+
+/// The value of an option, or `None` when the subcommand does not define it.
+fn value<T: Clone + Send + Sync + 'static>(matches: &ArgMatches, id: &str) -> Option<T> {
+    matches.try_get_one::<T>(id).ok().flatten().cloned()
+}
+
+fn values(matches: &ArgMatches, id: &str) -> Vec<String> {
+    matches
+        .try_get_many::<String>(id)
+        .ok()
+        .flatten()
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Whether the option standing for a positional slot was given, such as `--type` for
+/// CHUNK_TYPE.
+fn named(matches: &ArgMatches, slot: &str) -> bool {
+    let ids: &[&str] = match slot {
+        "CHUNK_TYPE" => &["type"],
+        "MESSAGE" => &["message", "message-file"],
+        "OUTPUT_PATH" => &["output"],
+        _ => &[],
+    };
+    ids.iter().any(|id| value::<String>(matches, id).is_some())
+}
+
+/// The positionals after FILE_PATH, moved into the slots no option has filled: with
+/// `--type`, `encode f.png -t ruSt hello` takes hello as the MESSAGE.
+///
+/// Every action reads OUTPUT_PATH, as in the original `ACTION FILE_PATH CHUNK_TYPE MESSAGE
+/// OUTPUT_PATH` form, so `remove f.png ruSt x out.png` still writes out.png. A slot the action
+/// does not read only takes a placeholder like x when a slot it reads follows; otherwise,
+/// as with `remove f.png ruSt out.png`, the value would be silently dropped and is rejected.
+fn positionals(action: Action, matches: &ArgMatches) -> Result<Vec<(String, String)>, ArgsErr> {
+    let slots = ["CHUNK_TYPE", "MESSAGE", "OUTPUT_PATH"];
+    let reads = action.slots();
+    let mut given = slots
+        .into_iter()
+        .filter_map(|slot| value::<String>(matches, slot));
+    let mut filled = vec![];
+    let mut placeholder = None;
+    for slot in slots.into_iter().filter(|slot| !named(matches, slot)) {
+        let Some(value) = given.next() else {
+            break;
+        };
+        if slot == "OUTPUT_PATH" || reads.iter().any(|read| read == slot) {
+            filled.push((slot.to_string(), value));
+            placeholder = None;
+        } else {
+            placeholder.get_or_insert(value);
+        }
+    }
+    match given.next().or(placeholder) {
+        Some(extra) => Err(ArgsErr::UnexpectedArgument(extra)),
+        None => Ok(filled),
+    }
+}
+
+/// A `--dpi` value that can be written to a pHYs chunk.
+fn parse_dpi(value: &str) -> Result<f64, String> {
+    let dpi = value
+        .parse::<f64>()
+        .map_err(|_| format!("{} is not a number", value))?;
+    match PixelDimensions::from_dpi(dpi) {
+        Ok(_) => Ok(dpi),
+        Err(physical_error) => Err(crate::Error::from(physical_error).report()),
+    }
+}
+
+fn flag(matches: &ArgMatches, id: &str) -> bool {
+    value::<bool>(matches, id).unwrap_or(false)
+}
+
+/// A switch that also has a `--no-` form: `Some` when either was given, the last one winning.
+fn switch(matches: &ArgMatches, id: &str) -> Option<bool> {
+    if flag(matches, id) {
+        Some(true)
+    } else if flag(matches, &format!("no-{}", id)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
 // impl ValueEnum for Action {
 //     fn value_variants<'a>() -> &'a [Self] {
 //         &[Action::Encode, Action::Decode, Action::Remove, Action::Print]
@@ -60,93 +538,353 @@ impl FromStr for Action {
 // }
 
 impl Arguments {
-    pub fn parse_arguments<'a>() -> Arguments {
-        let matches = Command::new("PngMe")
+    /// Parses the process arguments, with defaults from the config file.
+    pub fn parse_arguments() -> crate::Result<Arguments> {
+        let matches = Self::command().get_matches();
+        Self::from_matches(&matches, &Config::load()?)
+    }
+
+    /// The whole command line: one subcommand per action and the options they share.
+    pub fn command() -> Command {
+        Command::new("PngMe")
             .version("0.1")
             .author("Milton")
             .about("Does things involving pngs")
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .subcommands(Action::value_variants().iter().map(|action| action.command()))
             .arg(
-                arg!(<ACTION>)
-                    .help("What action do you want to perform")
-                    .value_parser(value_parser!(Action))
-                    .required(true),
+                arg!(-i --input <PATH>)
+                    .help("Another file, directory or glob pattern to process, can be given several times")
+                    .value_parser(value_parser!(String))
+                    .action(ArgAction::Append)
+                    .global(true),
             )
             .arg(
-                arg!(<FILE_PATH>)
-                    .help("Path to the file you want to operate on")
-                    .value_parser(value_parser!(String))
-                    .required(true),
+                arg!(-r --recursive)
+                    .help("Process every .png file in directories given as FILE_PATH or --input")
+                    .action(ArgAction::SetTrue)
+                    .global(true),
             )
             .arg(
-                arg!(<CHUNK_TYPE>)
-                    .help("Which chunk type would you like to target")
-                    .value_parser(value_parser!(String))
-                    .required(false),
+                arg!(--"fail-fast")
+                    .help("Stop processing a batch at the first file that fails")
+                    .action(ArgAction::SetTrue)
+                    .global(true),
+            )
+            .arg(
+                arg!(-j --jobs <JOBS>)
+                    .help("Number of files to process in parallel, defaults to the number of CPUs")
+                    .value_parser(value_parser!(usize))
+                    .global(true),
             )
             .arg(
-                arg!(<MESSAGE>)
-                    .help("What message would you like to embed in the file")
+                arg!(--backup [SUFFIX])
+                    .help("Keep the previous version of a file being overwritten, with this suffix (default .bak)")
                     .value_parser(value_parser!(String))
-                    .required(false),
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value(DEFAULT_BACKUP_SUFFIX)
+                    .global(true),
+            )
+            .arg(
+                arg!(--"dry-run")
+                    .help("Show the chunks a command would add, remove or modify without writing anything")
+                    .action(ArgAction::SetTrue)
+                    .global(true),
+            )
+            .arg(
+                arg!(--dpi <DPI>)
+                    .help("Set the pixel density (pHYs) of any written file to this many dots per inch")
+                    .value_parser(parse_dpi)
+                    .global(true),
+            )
+            .arg(
+                arg!(--touch)
+                    .help("Set the modification time (tIME) of any written file to now")
+                    .action(ArgAction::SetTrue)
+                    .overrides_with("no-touch")
+                    .global(true),
+            )
+            .arg(
+                arg!(--"no-touch")
+                    .help("Leave the modification time alone, even when the config sets touch")
+                    .action(ArgAction::SetTrue)
+                    .overrides_with("touch")
+                    .global(true),
+            )
+            .arg(
+                arg!(--"strip-trailer")
+                    .help("Drop any data found after IEND when writing the file")
+                    .action(ArgAction::SetTrue)
+                    .overrides_with("no-strip-trailer")
+                    .global(true),
+            )
+            .arg(
+                arg!(--"no-strip-trailer")
+                    .help("Keep data found after IEND, even when the config sets strip-trailer")
+                    .action(ArgAction::SetTrue)
+                    .overrides_with("strip-trailer")
+                    .global(true),
             )
             .arg(
-                arg!(<OUTPUT_PATH>)
-                    .help("Optional: Path for output")
+                arg!(--profile <NAME>)
+                    .help("Use the settings of this profile from the config file; run: perform its steps")
                     .value_parser(value_parser!(String))
-                    .required(false),
+                    .global(true),
             )
-            .get_matches();
-
-        let action = *matches
-            .get_one::<Action>("ACTION")
-            .expect("<ACTION> is required");
-
-        let file_path = matches
-            .get_one::<String>("FILE_PATH")
-            .expect("<FILE_PATH> is required");
-        let file_path = PathBuf::from(file_path);
+    }
 
-        let chunk_type = match matches.get_one::<String>("CHUNK_TYPE") {
-            Some(string) => Some(string.chars().collect()),
-            None => None,
-        };
+    /// The arguments for `matches` of [`Arguments::command`], filling in what they leave out
+    /// from `config` or the profile selected in it.
+    pub fn from_matches(matches: &ArgMatches, config: &Config) -> crate::Result<Arguments> {
+        let (name, matches) = matches.subcommand().expect("a subcommand is required");
+        let action = name
+            .parse::<Action>()
+            .expect("subcommands are built from the actions");
 
-        let message = match matches.get_one::<String>("MESSAGE") {
-            Some(string) => Some(string.chars().collect()),
-            None => None,
+        let config = match value::<String>(matches, "profile") {
+            Some(name) => config.with_profile(&name)?,
+            None => config.clone(),
         };
 
-        let output_path = match matches.get_one::<String>("OUTPUT_PATH") {
-            Some(path) => Some(PathBuf::from(path)),
-            None => None,
+        let file_path = value::<String>(matches, "FILE_PATH")
+            .map(PathBuf::from)
+            .expect("<FILE_PATH> is required");
+        let positionals = positionals(action, matches)?;
+        let positional = |slot: &str| {
+            positionals
+                .iter()
+                .find(|(id, _)| id == slot)
+                .map(|(_, value)| value.clone())
         };
 
-        let arguments = Arguments {
+        Ok(Arguments {
             action,
             file_path,
-            chunk_type,
-            message,
-            output_path,
-        };
-
-        arguments
+            inputs: values(matches, "input")
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            chunk_type: positional("CHUNK_TYPE").or(value(matches, "type")),
+            message: positional("MESSAGE").or(value(matches, "message")),
+            message_file: value::<String>(matches, "message-file").map(PathBuf::from),
+            output_path: positional("OUTPUT_PATH")
+                .or(value(matches, "output"))
+                .map(PathBuf::from),
+            framed: flag(matches, "framed"),
+            only: values(matches, "only"),
+            keep: values(matches, "keep"),
+            force: flag(matches, "force"),
+            strip_trailer: switch(matches, "strip-trailer"),
+            recursive: flag(matches, "recursive"),
+            fail_fast: flag(matches, "fail-fast"),
+            jobs: value(matches, "jobs"),
+            backup: value(matches, "backup"),
+            dry_run: flag(matches, "dry-run"),
+            critical_only: flag(matches, "critical-only"),
+            hex: switch(matches, "hex"),
+            offset: value(matches, "offset").unwrap_or(0),
+            length: value(matches, "length"),
+            frame: value(matches, "frame"),
+            to_srgb: flag(matches, "to-srgb"),
+            embed: value::<String>(matches, "embed").map(PathBuf::from),
+            dpi: value(matches, "dpi"),
+            touch: switch(matches, "touch"),
+            remove_gps: flag(matches, "remove-gps"),
+            orientation: value(matches, "orientation"),
+            placement: value(matches, "placement"),
+            steps: vec![],
+        }
+        .with_config(&config))
     }
 
-    pub fn action_has_enough_data(arguments: &Arguments) -> Result<(), ArgsErr> {
-        if arguments.action == Action::Encode
-            && arguments.chunk_type.is_none()
-            && arguments.message.is_none()
-        {
-            return Err(ArgsErr::MissingMessageAndChunkType);
+    /// Fills in what the command line left out from the config file, or from the profile
+    /// selected in it. Flags always win, and `--no-<switch>` turns off a switch the config sets.
+    ///
+    /// A configured chunk type only stands in for a missing CHUNK_TYPE, so encoding with it
+    /// takes the message from `--message`, `--message-file` or stdin.
+    pub fn with_config(mut self, config: &Config) -> Arguments {
+        self.placement = self.placement.or(config.placement());
+        self.backup = self.backup.or(config.backup().map(String::from));
+        self.strip_trailer = self.strip_trailer.or(config.strip_trailer());
+        self.hex = self.hex.or(config.hex());
+        if self.keep.is_empty() {
+            self.keep = config.keep().unwrap_or_default().to_vec();
         }
-        if arguments.action == Action::Decode && arguments.chunk_type.is_none() {
-            return Err(ArgsErr::MissingChunkType);
+        self.jobs = self.jobs.or(config.jobs());
+        self.dpi = self.dpi.or(config.dpi());
+        self.touch = self.touch.or(config.touch());
+        self.steps = config.steps().unwrap_or_default();
+
+        if let Some(default) = config.chunk_type() {
+            if matches!(
+                self.action,
+                Action::Encode | Action::Decode | Action::Remove
+            ) {
+                self.chunk_type.get_or_insert_with(|| default.to_string());
+            }
         }
-        if arguments.action == Action::Remove && arguments.chunk_type.is_none() {
-            return Err(ArgsErr::MissingChunkType);
+        self
+    }
+
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// FILE_PATH followed by every `--input`, before directories and globs are expanded.
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        std::iter::once(self.file_path.clone())
+            .chain(self.inputs.iter().cloned())
+            .collect()
+    }
+
+    /// A copy of these arguments acting on a single file of a batch.
+    pub fn for_file(&self, file_path: PathBuf, output_path: Option<PathBuf>) -> Arguments {
+        Arguments {
+            file_path,
+            inputs: vec![],
+            output_path,
+            ..self.clone()
+        }
+    }
+
+    pub fn chunk_type(&self) -> Option<&str> {
+        self.chunk_type.as_deref()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The file given with `--message-file`, whose contents encode hides.
+    pub fn message_file(&self) -> Option<&Path> {
+        self.message_file.as_deref()
+    }
+
+    pub fn output_path(&self) -> Option<&Path> {
+        self.output_path.as_deref()
+    }
+
+    pub fn framed(&self) -> bool {
+        self.framed
+    }
+
+    pub fn only(&self) -> &[String] {
+        &self.only
+    }
+
+    pub fn keep(&self) -> &[String] {
+        &self.keep
+    }
+
+    pub fn force(&self) -> bool {
+        self.force
+    }
+
+    pub fn strip_trailer(&self) -> bool {
+        self.strip_trailer.unwrap_or(false)
+    }
+
+    pub fn recursive(&self) -> bool {
+        self.recursive
+    }
+
+    pub fn fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn critical_only(&self) -> bool {
+        self.critical_only
+    }
+
+    pub fn hex(&self) -> bool {
+        self.hex.unwrap_or(false)
+    }
+
+    /// The APNG frame selected with `--frame`.
+    pub fn frame(&self) -> Option<usize> {
+        self.frame
+    }
+
+    pub fn to_srgb(&self) -> bool {
+        self.to_srgb
+    }
+
+    /// The ICC profile file given with `--embed`.
+    pub fn embed(&self) -> Option<&Path> {
+        self.embed.as_deref()
+    }
+
+    /// The pixel density to write, in dots per inch.
+    pub fn dpi(&self) -> Option<f64> {
+        self.dpi
+    }
+
+    pub fn touch(&self) -> bool {
+        self.touch.unwrap_or(false)
+    }
+
+    pub fn remove_gps(&self) -> bool {
+        self.remove_gps
+    }
+
+    /// The Exif orientation to write, 1 to 8.
+    pub fn orientation(&self) -> Option<u16> {
+        self.orientation
+    }
+
+    /// Where encode puts a new chunk.
+    pub fn placement(&self) -> Placement {
+        self.placement.unwrap_or_default()
+    }
+
+    /// The actions `run` performs, from the selected profile.
+    pub fn steps(&self) -> &[Action] {
+        &self.steps
+    }
+
+    /// A copy of these arguments performing step `index` of `run`. Later steps rewrite the
+    /// result of the first, so only the first backs up the original.
+    pub fn for_step(&self, index: usize, action: Action, file_path: PathBuf) -> Arguments {
+        Arguments {
+            action,
+            file_path,
+            backup: self.backup.clone().filter(|_| index == 0),
+            inputs: vec![],
+            steps: vec![],
+            ..self.clone()
         }
+    }
+
+    /// The window of chunk data to dump with `--hex`, as an offset and an optional length.
+    pub fn hex_range(&self) -> (usize, Option<usize>) {
+        (self.offset, self.length)
+    }
+
+    /// The suffix for backups of overwritten files, when `--backup` was given.
+    pub fn backup(&self) -> Option<&str> {
+        self.backup.as_deref()
+    }
 
-        Ok(())
+    pub fn action_has_enough_data(arguments: &Arguments) -> Result<(), ArgsErr> {
+        arguments
+            .action
+            .rules()
+            .iter()
+            .try_for_each(|rule| rule.check(arguments))
     }
 }
 
@@ -154,6 +892,12 @@ impl Arguments {
 pub enum ArgsErr {
     MissingChunkType,
     MissingMessageAndChunkType,
+    MissingChunkFile,
+    MissingDestination,
+    MissingComparedFile,
+    NothingToSet,
+    NoSteps,
+    UnexpectedArgument(String),
 }
 
 impl std::error::Error for ArgsErr {}
@@ -168,7 +912,163 @@ impl Display for ArgsErr {
             Self::MissingMessageAndChunkType => write!(
                 f,
                 "Missing Chunk Type and Message from your argument list, use -h flag to learn how to use"
-            )
+            ),
+            Self::MissingChunkFile => write!(
+                f,
+                "Missing chunk file from your argument list, use -h flag to learn how to use"
+            ),
+            Self::MissingDestination => write!(
+                f,
+                "Missing destination PNG from your argument list, use -h flag to learn how to use"
+            ),
+            Self::MissingComparedFile => write!(
+                f,
+                "Missing second PNG to compare from your argument list, use -h flag to learn how to use"
+            ),
+            Self::NothingToSet => write!(
+                f,
+                "Nothing to set, give --dpi or --touch, use -h flag to learn how to use"
+            ),
+            Self::NoSteps => write!(
+                f,
+                "Nothing to run, give --profile with a profile that has steps, use -h flag to learn how to use"
+            ),
+            Self::UnexpectedArgument(argument) => write!(
+                f,
+                "Unexpected argument {}, use -h flag to learn how to use",
+                argument
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_with(config: &str, line: &[&str]) -> crate::Result<Arguments> {
+        let matches = Arguments::command()
+            .try_get_matches_from(std::iter::once("pngme").chain(line.iter().copied()))
+            .map_err(|clap_error| crate::Error::Usage(Box::new(clap_error)))?;
+        Arguments::from_matches(&matches, &Config::parse(config).unwrap())
+    }
+
+    fn parse(line: &[&str]) -> crate::Result<Arguments> {
+        parse_with("", line)
+    }
+
+    #[test]
+    fn test_positional_forms() {
+        let encode = parse(&["encode", "f.png", "ruSt", "hello", "out.png"]).unwrap();
+        assert_eq!(encode.chunk_type(), Some("ruSt"));
+        assert_eq!(encode.message(), Some("hello"));
+        assert_eq!(encode.output_path(), Some(Path::new("out.png")));
+
+        let insert = parse(&["insert", "f.png", "iCCP", "icc.bin"]).unwrap();
+        assert_eq!(insert.chunk_type(), Some("iCCP"));
+        assert_eq!(insert.message(), Some("icc.bin"));
+
+        let diff = parse(&["diff", "a.png", "b.png"]).unwrap();
+        assert_eq!(diff.chunk_type(), Some("b.png"));
+        let transplant = parse(&["transplant", "a.png", "b.png", "--only", "tEXt"]).unwrap();
+        assert_eq!(transplant.chunk_type(), Some("b.png"));
+        assert_eq!(transplant.only(), ["tEXt"]);
+    }
+
+    #[test]
+    fn test_named_forms() {
+        let encode = parse(&[
+            "encode",
+            "f.png",
+            "--type",
+            "ruSt",
+            "--message-file",
+            "secret.txt",
+            "--output",
+            "hidden.png",
+        ])
+        .unwrap();
+        assert_eq!(encode.chunk_type(), Some("ruSt"));
+        assert_eq!(encode.message_file(), Some(Path::new("secret.txt")));
+        assert_eq!(encode.output_path(), Some(Path::new("hidden.png")));
+
+        let shifted = parse(&["encode", "f.png", "-t", "ruSt", "hello", "out.png"]).unwrap();
+        assert_eq!(shifted.chunk_type(), Some("ruSt"));
+        assert_eq!(shifted.message(), Some("hello"));
+        assert_eq!(shifted.output_path(), Some(Path::new("out.png")));
+
+        let decode = parse(&["decode", "f.png", "-t", "ruSt"]).unwrap();
+        assert_eq!(decode.chunk_type(), Some("ruSt"));
+    }
+
+    #[test]
+    fn test_placeholder_slots() {
+        let remove = parse(&["remove", "f.png", "ruSt", "x", "out.png"]).unwrap();
+        assert_eq!(remove.chunk_type(), Some("ruSt"));
+        assert_eq!(remove.output_path(), Some(Path::new("out.png")));
+        let decode = parse(&["decode", "f.png", "-t", "ruSt", "x", "out.png"]).unwrap();
+        assert_eq!(decode.output_path(), Some(Path::new("out.png")));
+        let print = parse(&["print", "f.png", "x", "y", "out.png"]).unwrap();
+        assert_eq!(print.output_path(), Some(Path::new("out.png")));
+
+        assert!(parse(&["remove", "f.png", "ruSt", "out.png"]).is_err());
+        assert!(parse(&["print", "f.png", "ruSt"]).is_err());
+        assert!(parse(&["decode", "f.png", "-t", "ruSt", "ruSt"]).is_err());
+    }
+
+    #[test]
+    fn test_dpi_must_be_storable() {
+        assert_eq!(
+            parse(&["set", "f.png", "--dpi", "300"]).unwrap().dpi(),
+            Some(300.0)
+        );
+        for dpi in ["0", "-72", "NaN", "inf", "1e12", "lots"] {
+            assert!(parse(&["set", "f.png", &format!("--dpi={}", dpi)]).is_err());
         }
     }
+
+    #[test]
+    fn test_rules() {
+        let enough = |line: &[&str]| Arguments::action_has_enough_data(&parse(line).unwrap());
+        assert!(matches!(
+            enough(&["decode", "f.png"]),
+            Err(ArgsErr::MissingChunkType)
+        ));
+        assert!(matches!(
+            enough(&["encode", "f.png"]),
+            Err(ArgsErr::MissingMessageAndChunkType)
+        ));
+        assert!(matches!(
+            enough(&["insert", "f.png"]),
+            Err(ArgsErr::MissingChunkFile)
+        ));
+        assert!(matches!(
+            enough(&["diff", "a.png"]),
+            Err(ArgsErr::MissingComparedFile)
+        ));
+        assert!(matches!(
+            enough(&["set", "f.png"]),
+            Err(ArgsErr::NothingToSet)
+        ));
+        assert!(matches!(enough(&["run", "f.png"]), Err(ArgsErr::NoSteps)));
+        assert!(enough(&["set", "f.png", "--touch"]).is_ok());
+        assert!(enough(&["print", "f.png"]).is_ok());
+    }
+
+    #[test]
+    fn test_flags_override_config() {
+        let config = "hex = true\ntouch = true\n[profiles.quiet]\nhex = false";
+        assert!(parse_with(config, &["print", "f.png"]).unwrap().hex());
+        assert!(!parse_with(config, &["print", "f.png", "--no-hex"])
+            .unwrap()
+            .hex());
+        assert!(
+            !parse_with(config, &["print", "f.png", "--profile", "quiet"])
+                .unwrap()
+                .hex()
+        );
+        assert!(!parse_with(config, &["set", "f.png", "--no-touch"])
+            .unwrap()
+            .touch());
+    }
 }
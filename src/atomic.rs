@@ -0,0 +1,120 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_BACKUP_SUFFIX: &str = ".bak";
+
+/// Replaces the file at `path` with `bytes` without ever leaving it half written.
+///
+/// The bytes go to a temporary file in the same directory which is synced and then renamed
+/// over `path`, so a crash leaves either the old or the new file. When `path` already
+/// exists its permissions and modification time carry over to the new file, and with a
+/// `backup_suffix` the previous version is kept next to it under that suffix.
+pub fn write_atomic(path: &Path, bytes: &[u8], backup_suffix: Option<&str>) -> io::Result<()> {
+    let original = fs::metadata(path).ok();
+    let temp_path = temp_path(path);
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)?;
+        file.write_all(bytes)?;
+        if let Some(metadata) = original.as_ref() {
+            file.set_permissions(metadata.permissions())?;
+            file.set_modified(metadata.modified()?)?;
+        }
+        file.sync_all()?;
+
+        if let (Some(suffix), Some(_)) = (backup_suffix, original.as_ref()) {
+            backup(path, suffix)?;
+        }
+        fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    sync_directory(path)
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()))
+}
+
+fn backup(path: &Path, suffix: &str) -> io::Result<()> {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(suffix);
+    let backup_path = PathBuf::from(backup_path);
+
+    if backup_path.exists() {
+        fs::remove_file(&backup_path)?;
+    }
+    if fs::hard_link(path, &backup_path).is_err() {
+        fs::copy(path, &backup_path)?;
+    }
+    Ok(())
+}
+
+/// Makes the rename itself durable. Only possible on Unix, where directories can be opened.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("pngme-atomic-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_write_atomic_new_file() {
+        let path = temp_directory("new").join("image.png");
+        write_atomic(&path, b"new", None).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+    }
+
+    #[test]
+    fn test_write_atomic_keeps_metadata_and_backup() {
+        let directory = temp_directory("replace");
+        let path = directory.join("image.png");
+        fs::write(&path, b"old").unwrap();
+        let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1 << 30);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        write_atomic(&path, b"new", Some(DEFAULT_BACKUP_SUFFIX)).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read(directory.join("image.png.bak")).unwrap(), b"old");
+        assert!(metadata.permissions().readonly());
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 2);
+    }
+}
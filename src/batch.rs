@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::{fmt, fmt::Display, fs, thread};
+
+use crate::args::Arguments;
+use crate::commands;
+use crate::error::Error;
+
+const GLOB_CHARACTERS: [char; 3] = ['*', '?', '['];
+
+/// The result of running the action on one file of a batch.
+pub struct FileOutcome {
+    path: PathBuf,
+    output: Vec<u8>,
+    log: Vec<u8>,
+    result: Option<Result<(), Error>>,
+}
+
+impl FileOutcome {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whatever the action wrote while processing this file.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Whatever the action logged while processing this file.
+    pub fn log(&self) -> &[u8] {
+        &self.log
+    }
+
+    /// `None` when the file was skipped because an earlier file failed with `--fail-fast`.
+    pub fn result(&self) -> Option<&Result<(), Error>> {
+        self.result.as_ref()
+    }
+}
+
+pub struct BatchSummary {
+    outcomes: Vec<FileOutcome>,
+}
+
+impl BatchSummary {
+    pub fn outcomes(&self) -> &[FileOutcome] {
+        &self.outcomes
+    }
+
+    pub fn succeeded(&self) -> usize {
+        self.count(|result| matches!(result, Some(Ok(()))))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|result| matches!(result, Some(Err(_))))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|result| result.is_none())
+    }
+
+    /// The error of the first file that failed, wrapped with the number of failures.
+    pub fn into_error(self) -> Option<Error> {
+        let failed = self.failed();
+        let total = self.outcomes.len();
+        self.outcomes
+            .into_iter()
+            .find_map(|outcome| outcome.result.and_then(Result::err))
+            .map(|first| Error::Batch {
+                failed,
+                total,
+                first: Box::new(first),
+            })
+    }
+
+    fn count<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(Option<&Result<(), Error>>) -> bool,
+    {
+        self.outcomes
+            .iter()
+            .filter(|outcome| predicate(outcome.result()))
+            .count()
+    }
+}
+
+impl Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for outcome in self.outcomes.iter() {
+            match outcome.result() {
+                Some(Ok(())) => writeln!(f, "ok      {}", outcome.path().display())?,
+                Some(Err(error)) => writeln!(
+                    f,
+                    "failed  {}: {}",
+                    outcome.path().display(),
+                    error.report()
+                )?,
+                None => writeln!(f, "skipped {}", outcome.path().display())?,
+            }
+        }
+        write!(
+            f,
+            "{} succeeded, {} failed, {} skipped",
+            self.succeeded(),
+            self.failed(),
+            self.skipped()
+        )
+    }
+}
+
+/// Turns the paths given on the command line into the list of files to process.
+///
+/// Plain files are kept as they are, even if they do not exist, so that the error is
+/// reported against that file. Directories need `recursive` and contribute every `.png`
+/// below them. Anything else containing `*`, `?` or `[` is expanded as a glob pattern.
+pub fn expand_inputs(inputs: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>, BatchError> {
+    let mut files = vec![];
+
+    for input in inputs {
+        if input.is_dir() {
+            if !recursive {
+                return Err(BatchError::DirectoryWithoutRecursive(input.clone()));
+            }
+            collect_pngs(input, &mut files)?;
+        } else if !input.exists() && is_glob(input) {
+            let pattern = input.to_string_lossy();
+            let paths =
+                glob::glob(&pattern).map_err(|e| BatchError::Pattern(pattern.to_string(), e))?;
+            let before = files.len();
+            for path in paths.filter_map(|path| path.ok()) {
+                match path.is_dir() {
+                    true if recursive => collect_pngs(&path, &mut files)?,
+                    true => continue,
+                    false => files.push(path),
+                }
+            }
+            if files.len() == before {
+                return Err(BatchError::NoMatches(pattern.to_string()));
+            }
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Runs the action on every file across a pool of `jobs` worker threads.
+///
+/// Each file is processed with its own copy of the arguments, writing to `output_directory`
+/// when one is given. A failure is recorded against its file and the batch carries on,
+/// unless `fail_fast` is set, in which case files not yet started are skipped.
+pub fn run(
+    arguments: &Arguments,
+    files: &[PathBuf],
+    output_directory: Option<&Path>,
+    jobs: usize,
+    fail_fast: bool,
+) -> BatchSummary {
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let outcomes: Mutex<Vec<FileOutcome>> = Mutex::new(
+        files
+            .iter()
+            .map(|path| FileOutcome {
+                path: path.clone(),
+                output: vec![],
+                log: vec![],
+                result: None,
+            })
+            .collect(),
+    );
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, files.len().max(1)) {
+            scope.spawn(|| loop {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(path) = files.get(index) else {
+                    break;
+                };
+
+                let output_path = output_directory.map(|directory| output_path(directory, path));
+                let file_arguments = arguments.for_file(path.clone(), output_path);
+                let mut output = vec![];
+                let mut log = vec![];
+                let result = commands::run(
+                    &file_arguments,
+                    &mut std::io::empty(),
+                    &mut output,
+                    &mut log,
+                );
+
+                if result.is_err() && fail_fast {
+                    stop.store(true, Ordering::SeqCst);
+                }
+                let mut outcomes = outcomes.lock().unwrap();
+                outcomes[index].output = output;
+                outcomes[index].log = log;
+                outcomes[index].result = Some(result);
+            });
+        }
+    });
+
+    BatchSummary {
+        outcomes: outcomes.into_inner().unwrap(),
+    }
+}
+
+/// Fails when two files of the batch would be written to the same file of `output_directory`,
+/// as happens with `--recursive` when nested directories hold files of the same name.
+pub fn check_outputs(files: &[PathBuf], output_directory: &Path) -> Result<(), BatchError> {
+    let mut written: HashMap<PathBuf, &PathBuf> = HashMap::new();
+    for path in files {
+        let output = output_path(output_directory, path);
+        if let Some(first) = written.insert(output.clone(), path) {
+            return Err(BatchError::OutputCollision(
+                first.clone(),
+                path.clone(),
+                output,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn output_path(directory: &Path, path: &Path) -> PathBuf {
+    directory.join(path.file_name().unwrap_or_default())
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(GLOB_CHARACTERS)
+}
+
+fn collect_pngs(directory: &Path, files: &mut Vec<PathBuf>) -> Result<(), BatchError> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|e| BatchError::ReadDirectory(directory.to_path_buf(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_pngs(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    DirectoryWithoutRecursive(PathBuf),
+    Pattern(String, glob::PatternError),
+    NoMatches(String),
+    ReadDirectory(PathBuf, std::io::Error),
+    OutputCollision(PathBuf, PathBuf, PathBuf),
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DirectoryWithoutRecursive(path) => write!(
+                f,
+                "Error: {} is a directory, use --recursive to process the files in it",
+                path.display()
+            ),
+            Self::Pattern(pattern, pattern_error) => {
+                write!(f, "Error: invalid pattern {}: {}", pattern, pattern_error)
+            }
+            Self::NoMatches(pattern) => write!(f, "Error: no files match {}", pattern),
+            Self::ReadDirectory(path, io_error) => {
+                write!(f, "Error: could not read {}: {}", path.display(), io_error)
+            }
+            Self::OutputCollision(first, second, output) => write!(
+                f,
+                "Error: {} and {} would both be written to {}, process them separately",
+                first.display(),
+                second.display(),
+                output.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("pngme-batch-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        for file in ["a.png", "b.PNG", "notes.txt", "nested/c.png"] {
+            fs::write(directory.join(file), b"").unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn test_expand_directory_needs_recursive() {
+        let directory = temp_directory("recursive");
+        let result = expand_inputs(std::slice::from_ref(&directory), false);
+        assert!(matches!(
+            result,
+            Err(BatchError::DirectoryWithoutRecursive(_))
+        ));
+
+        let files = expand_inputs(std::slice::from_ref(&directory), true).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                directory.join("a.png"),
+                directory.join("b.PNG"),
+                directory.join("nested/c.png")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let directory = temp_directory("glob");
+        let pattern = directory.join("*.png");
+        let files = expand_inputs(&[pattern], false).unwrap();
+        assert_eq!(files, vec![directory.join("a.png")]);
+
+        let pattern = directory.join("*.gif");
+        assert!(matches!(
+            expand_inputs(&[pattern], false),
+            Err(BatchError::NoMatches(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_outputs_rejects_same_name() {
+        let directory = temp_directory("outputs");
+        fs::write(directory.join("nested/a.png"), b"").unwrap();
+        let files = expand_inputs(std::slice::from_ref(&directory), true).unwrap();
+        assert!(matches!(
+            check_outputs(&files, Path::new("out")),
+            Err(BatchError::OutputCollision(first, second, output))
+                if first == directory.join("a.png")
+                    && second == directory.join("nested/a.png")
+                    && output == Path::new("out/a.png")
+        ));
+        assert!(check_outputs(&files[1..], Path::new("out")).is_ok());
+    }
+
+    #[test]
+    fn test_expand_keeps_plain_files() {
+        let missing = PathBuf::from("does/not/exist.png");
+        let files = expand_inputs(std::slice::from_ref(&missing), false).unwrap();
+        assert_eq!(files, vec![missing]);
+    }
+}
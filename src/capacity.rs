@@ -0,0 +1,148 @@
+use std::{fmt, fmt::Display};
+
+use crate::chunk::MAX_DATA_LENGTH;
+use crate::ihdr::{ColorType, Ihdr};
+use crate::text::MAX_KEYWORD_LENGTH;
+
+/// How many payload bytes one hiding method can carry in an image, and what limits it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Capacity {
+    method: &'static str,
+    bytes: Option<u64>,
+    constraints: String,
+}
+
+impl Capacity {
+    pub fn method(&self) -> &'static str {
+        self.method
+    }
+
+    /// The payload size in bytes, or `None` when the method cannot be used on this image.
+    pub fn bytes(&self) -> Option<u64> {
+        self.bytes
+    }
+
+    pub fn constraints(&self) -> &str {
+        &self.constraints
+    }
+}
+
+impl Display for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bytes {
+            Some(bytes) => write!(
+                f,
+                "{:<5} {} bytes, {}",
+                self.method, bytes, self.constraints
+            ),
+            None => write!(f, "{:<5} unavailable, {}", self.method, self.constraints),
+        }
+    }
+}
+
+/// The capacity of every way a message can be stored in an image with this header.
+///
+/// Chunk and text capacities are per chunk, as any number of chunks can be added. The text
+/// figures assume a one-character keyword, which counts against the same limit.
+pub fn capacities(ihdr: &Ihdr) -> Vec<Capacity> {
+    let max = MAX_DATA_LENGTH as u64;
+    let keyword = format!(
+        "keyword of 1 to {} Latin-1 characters counted in the limit",
+        MAX_KEYWORD_LENGTH
+    );
+
+    vec![
+        Capacity {
+            method: "chunk",
+            bytes: Some(max),
+            constraints: "per chunk, any number of chunks, APNG chunk types excluded".to_string(),
+        },
+        Capacity {
+            method: "tEXt",
+            bytes: Some(max - 2),
+            constraints: format!("per chunk, {}, Latin-1 text", keyword),
+        },
+        Capacity {
+            method: "zTXt",
+            bytes: Some(max - 3),
+            constraints: format!(
+                "deflated bytes per chunk, more text fits as it compresses, {}, Latin-1 text",
+                keyword
+            ),
+        },
+        Capacity {
+            method: "iTXt",
+            bytes: Some(max - 6),
+            constraints: format!(
+                "per chunk, {}, UTF-8 text, language tag and translated keyword counted too",
+                keyword
+            ),
+        },
+        lsb_capacity(ihdr, 1),
+        lsb_capacity(ihdr, 2),
+        lsb_capacity(ihdr, 4),
+    ]
+}
+
+/// `bits` low bits of every sample. Palette indices are left out since changing their low
+/// bits can select an unrelated colour, and samples of no more than `bits` bits since the
+/// payload would replace them entirely.
+fn lsb_capacity(ihdr: &Ihdr, bits: u8) -> Capacity {
+    let method = match bits {
+        1 => "LSB1",
+        2 => "LSB2",
+        _ => "LSB4",
+    };
+    let unavailable = if ihdr.color_type() == ColorType::Indexed {
+        Some("indexed colour samples are palette indices".to_string())
+    } else if ihdr.bit_depth() <= bits {
+        Some(format!("samples only have {} bits", ihdr.bit_depth()))
+    } else {
+        None
+    };
+    if let Some(constraints) = unavailable {
+        return Capacity {
+            method,
+            bytes: None,
+            constraints,
+        };
+    }
+    let samples = ihdr.pixel_count() * ihdr.color_type().channels() as u64;
+    Capacity {
+        method,
+        bytes: Some(samples * bits as u64 / 8),
+        constraints: format!(
+            "{} low bit{} in each of {} samples ({} pixels of {} bits), raw without any \
+             envelope as encode cannot write pixel data yet",
+            bits,
+            if bits == 1 { "" } else { "s" },
+            samples,
+            ihdr.pixel_count(),
+            ihdr.bits_per_pixel()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ihdr;
+
+    #[test]
+    fn test_capacities() {
+        let all = capacities(&ihdr(8, 6));
+        assert_eq!(all[0].bytes(), Some(2_147_483_647));
+        assert_eq!(all[1].bytes(), Some(2_147_483_645));
+
+        let lsb: Vec<_> = all[4..].iter().map(|capacity| capacity.bytes()).collect();
+        assert_eq!(all[4].method(), "LSB1");
+        assert_eq!(lsb, [Some(500), Some(1000), Some(2000)]);
+        assert_eq!(capacities(&ihdr(8, 3))[4].bytes(), None);
+
+        let shallow: Vec<_> = capacities(&ihdr(2, 0))[4..]
+            .iter()
+            .map(|capacity| capacity.bytes())
+            .collect();
+        assert_eq!(shallow, [Some(125), None, None]);
+    }
+}
@@ -1,13 +1,16 @@
 use std::{fmt, fmt::Display};
 use std::{string::FromUtf8Error, string::String};
 
-use crate::chunk_type::ChunkType;
+use crate::chunk_type::{ChunkType, ChunkTypeError};
 
 const DATA_TYPE_BYTES: usize = 4;
 const CRC_BYTES: usize = 4;
 pub const DATA_LENGTH_BYTES: usize = 4;
 pub const META_DATA_BYTES: usize = DATA_TYPE_BYTES + CRC_BYTES + DATA_LENGTH_BYTES;
+/// The largest data length the PNG specification allows for one chunk.
+pub const MAX_DATA_LENGTH: usize = (1 << 31) - 1;
 
+#[derive(Clone)]
 pub struct Chunk {
     chunk_type: ChunkType,
     data: Vec<u8>,
@@ -40,8 +43,43 @@ impl Chunk {
         &self.data
     }
 
+    /// One line with the type, length and what the type's property bits say about it.
+    pub fn summary(&self) -> String {
+        let kind = match self.chunk_type.is_critical() {
+            true => "critical",
+            false => "ancillary",
+        };
+        let visibility = match self.chunk_type.is_public() {
+            true => "public",
+            false => "private",
+        };
+        let copying = match self.chunk_type.is_safe_to_copy() {
+            true => "safe to copy",
+            false => "unsafe to copy",
+        };
+        format!(
+            "{} ({} bytes), {}, {}, {}",
+            self.chunk_type.to_string(),
+            self.length(),
+            kind,
+            visibility,
+            copying
+        )
+    }
+
+    /// Parses a chunk file holding exactly one framed chunk, rejecting bytes after its CRC.
+    ///
+    /// `try_from` stops at the CRC so that it can walk a chunk stream.
+    pub fn from_framed(bytes: &[u8]) -> Result<Chunk, ChunkError> {
+        let chunk = Chunk::try_from(bytes)?;
+        match bytes.len() - (chunk.data.len() + META_DATA_BYTES) {
+            0 => Ok(chunk),
+            extra => Err(ChunkError::TrailingBytes(extra)),
+        }
+    }
+
     pub fn data_as_string(&self) -> Result<String, FromUtf8Error> {
-        String::from_utf8(self.data.iter().copied().collect())
+        String::from_utf8(self.data.clone())
     }
 
     fn crc(&self) -> u32 {
@@ -93,6 +131,10 @@ impl TryFrom<&[u8]> for Chunk {
             Err(chunk_type_err) => return Err(ChunkError::ParsingChunkType(chunk_type_err)),
         };
 
+        if value.len() < data_length + CRC_BYTES {
+            return Err(ChunkError::DataTruncated(data_length, value.len()));
+        }
+
         let (data, value) = value.split_at(data_length);
 
         let (crc, _) = value.split_at(CRC_BYTES);
@@ -120,11 +162,13 @@ impl TryFrom<&[u8]> for Chunk {
 #[derive(Debug)]
 pub enum ChunkError {
     DataSampleSmall(usize),
+    DataTruncated(usize, usize),
     ParsingDataLength,
     ParsingDataType,
-    ParsingChunkType(&'static str),
+    ParsingChunkType(ChunkTypeError),
     ParsingCrc,
     CrcNotMatching(u32, u32),
+    TrailingBytes(usize),
 }
 
 impl Display for ChunkError {
@@ -135,22 +179,35 @@ impl Display for ChunkError {
                 "Error: size of data to small, must be at least {} bytes, data was {} bytes",
                 META_DATA_BYTES, length
             ),
+            Self::DataTruncated(data_length, available) => write!(
+                f,
+                "Error: chunk declares {} bytes of data plus CRC but only {} bytes are left",
+                data_length, available
+            ),
             Self::ParsingDataLength => write!(f, "Error: Could not parse file's meta data"),
             Self::ParsingDataType => write!(f, "Error: Could not parse file's data type"),
-            Self::ParsingChunkType(chunk_type_error) => {
-                write!(f, "Error: Could not parse chunk type: {}", chunk_type_error)
-            }
+            Self::ParsingChunkType(_) => write!(f, "Error: Could not parse chunk type"),
             Self::ParsingCrc => write!(f, "Error: Could not parse CRC"),
             Self::CrcNotMatching(parsed_crc, calculated_crc) => write!(
                 f,
                 "Error: CRC not matching. Parsed CRC is {} and calculated CRC is {}",
                 parsed_crc, calculated_crc
             ),
+            Self::TrailingBytes(extra) => {
+                write!(f, "Error: {} bytes follow the chunk's CRC", extra)
+            }
         }
     }
 }
 
-impl std::error::Error for ChunkError {}
+impl std::error::Error for ChunkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ParsingChunkType(chunk_type_error) => Some(chunk_type_error),
+            _ => None,
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -207,6 +264,15 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_chunk_summary() {
+        let chunk = testing_chunk();
+        assert_eq!(
+            chunk.summary(),
+            "RuSt (42 bytes), critical, private, safe to copy"
+        );
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
@@ -261,6 +327,38 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_truncated_chunk_from_bytes() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where".as_bytes();
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref());
+
+        assert!(matches!(chunk, Err(ChunkError::DataTruncated(42, 13))));
+    }
+
+    #[test]
+    fn test_framed_chunk_rejects_trailing_bytes() {
+        let mut bytes = testing_chunk().as_bytes();
+        assert!(Chunk::from_framed(&bytes).is_ok());
+
+        bytes.extend_from_slice(b"extra");
+        assert!(Chunk::try_from(bytes.as_ref()).is_ok());
+        assert!(matches!(
+            Chunk::from_framed(&bytes),
+            Err(ChunkError::TrailingBytes(5))
+        ));
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -2,7 +2,7 @@ use std::fmt;
 use std::fmt::Display;
 use std::str::FromStr;
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub struct ChunkType {
     string_value: [char; 4],
     numeric_value: [u8; 4],
@@ -37,6 +37,7 @@ impl ChunkType {
         self.is_reserved_bit_valid()
     }
 
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         self.string_value.iter().collect()
     }
@@ -51,25 +52,25 @@ impl Display for ChunkType {
     }
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 enum Private {
     Private,
     Public,
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 enum Reserved {
     Reserved,
     NotReserved,
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 enum SafeToCopy {
     SafeToCopy,
     UnsafeToCopy,
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 enum Ancillary {
     Critical,
     Ancillary,
@@ -112,19 +113,11 @@ impl Display for Ancillary {
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = &'static str;
+    type Error = ChunkTypeError;
 
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        for i in value {
-            if i < 65 || (i >= 91 && i <= 96) || i > 122 {
-                return Result::Err(
-                    r#"
-Error:
-Number must be a valid utf8 alpha numeric character
-Remember A - Z = 65 - 90 and a - z = 97 - 122
-Number outside of valid range, check PNG documentation to see valid range"#,
-                );
-            }
+        if let Some(index) = value.iter().position(|byte| !byte.is_ascii_alphabetic()) {
+            return Err(ChunkTypeError::InvalidByte(index, value[index]));
         }
 
         let string_value = [
@@ -166,11 +159,11 @@ Number outside of valid range, check PNG documentation to see valid range"#,
 }
 
 impl FromStr for ChunkType {
-    type Err = &'static str;
+    type Err = ChunkTypeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() != 4 {
-            return Result::Err("A chunk must have 4 chars");
+            return Err(ChunkTypeError::InvalidLength(s.len()));
         }
         let numeric_value = s.as_bytes();
         let numeric_value: [u8; 4] = [
@@ -184,6 +177,32 @@ impl FromStr for ChunkType {
     }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ChunkTypeError {
+    InvalidLength(usize),
+    /// The position and value of a byte outside A-Z and a-z.
+    InvalidByte(usize, u8),
+}
+
+impl Display for ChunkTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(
+                f,
+                "Error: a chunk type must have 4 characters, found {}",
+                length
+            ),
+            Self::InvalidByte(index, byte) => write!(
+                f,
+                "Error: byte {} of the chunk type is {}, it must be a letter, A-Z (65-90) or a-z (97-122)",
+                index, byte
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChunkTypeError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
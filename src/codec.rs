@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+use std::{fmt, fmt::Display};
+
+use crate::apng::{AnimationControlCodec, FrameControlCodec, FrameDataCodec};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::color::{ChromaticitiesCodec, CicpCodec, GammaCodec, IccProfileCodec, SrgbCodec};
+use crate::exif::ExifCodec;
+use crate::ihdr::Ihdr;
+use crate::palette::{PaletteCodec, SuggestedPaletteCodec};
+use crate::physical::{
+    ModificationTimeCodec, OffsetsCodec, PhysicalScaleCodec, PixelDimensionsCodec,
+};
+use crate::text::TextChunk;
+
+/// Decodes the data of one chunk type into a typed value and encodes it back.
+///
+/// Implement this for private chunks with their own binary layout and add it with
+/// [`register`] so `print`, `show` and the edit shell render them through `Display`.
+pub trait ChunkCodec: Send + Sync {
+    type Value: Display;
+
+    /// The four-character code of the chunks this codec reads and writes.
+    fn chunk_type(&self) -> ChunkType;
+
+    fn decode(&self, data: &[u8]) -> crate::Result<Self::Value>;
+
+    fn encode(&self, value: &Self::Value) -> crate::Result<Vec<u8>>;
+
+    /// Decodes a chunk, failing when it is not of this codec's type.
+    fn decode_chunk(&self, chunk: &Chunk) -> crate::Result<Self::Value> {
+        if chunk.chunk_type() != &self.chunk_type() {
+            return Err(CodecError::WrongChunkType(chunk.chunk_type().to_string()).into());
+        }
+        self.decode(chunk.data())
+    }
+
+    fn encode_chunk(&self, value: &Self::Value) -> crate::Result<Chunk> {
+        Ok(Chunk::new(self.chunk_type(), self.encode(value)?))
+    }
+}
+
+/// A codec with its value type erased, so codecs of different types can share a registry.
+trait Renderer: Send + Sync {
+    fn render(&self, data: &[u8]) -> crate::Result<String>;
+}
+
+impl<C: ChunkCodec> Renderer for C {
+    fn render(&self, data: &[u8]) -> crate::Result<String> {
+        Ok(self.decode(data)?.to_string())
+    }
+}
+
+/// Codecs keyed by chunk type. A codec registered for a type replaces the previous one.
+pub struct CodecRegistry {
+    codecs: HashMap<String, Box<dyn Renderer>>,
+}
+
+impl CodecRegistry {
+    pub fn empty() -> CodecRegistry {
+        CodecRegistry {
+            codecs: HashMap::new(),
+        }
+    }
+
+    pub fn register<C: ChunkCodec + 'static>(&mut self, codec: C) {
+        self.codecs
+            .insert(codec.chunk_type().to_string(), Box::new(codec));
+    }
+
+    pub fn has_codec(&self, chunk_type: &str) -> bool {
+        self.codecs.contains_key(chunk_type)
+    }
+
+    /// The decoded value of the chunk as text, or `None` when no codec handles its type.
+    pub fn render(&self, chunk: &Chunk) -> Option<crate::Result<String>> {
+        let codec = self.codecs.get(&chunk.chunk_type().to_string())?;
+        Some(codec.render(chunk.data()))
+    }
+}
+
+impl Default for CodecRegistry {
+    /// A registry holding the codecs this crate ships for standard chunks.
+    fn default() -> Self {
+        let mut registry = CodecRegistry::empty();
+        registry.register(IhdrCodec);
+        for chunk_type in ["tEXt", "zTXt", "iTXt"] {
+            registry.register(TextCodec::new(chunk_type));
+        }
+        registry.register(AnimationControlCodec);
+        registry.register(FrameControlCodec);
+        registry.register(FrameDataCodec);
+        registry.register(GammaCodec);
+        registry.register(ChromaticitiesCodec);
+        registry.register(SrgbCodec);
+        registry.register(IccProfileCodec);
+        registry.register(CicpCodec);
+        registry.register(PaletteCodec);
+        registry.register(SuggestedPaletteCodec);
+        registry.register(PixelDimensionsCodec);
+        registry.register(ModificationTimeCodec);
+        registry.register(OffsetsCodec);
+        registry.register(PhysicalScaleCodec);
+        registry.register(ExifCodec);
+        registry
+    }
+}
+
+/// The registry the commands render chunks with, starting with the default codecs.
+pub fn global() -> &'static RwLock<CodecRegistry> {
+    static REGISTRY: OnceLock<RwLock<CodecRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(CodecRegistry::default()))
+}
+
+/// Adds a codec to the registry used by the commands.
+pub fn register<C: ChunkCodec + 'static>(codec: C) {
+    global()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .register(codec);
+}
+
+/// Renders a chunk with the registry used by the commands.
+pub fn render(chunk: &Chunk) -> Option<crate::Result<String>> {
+    global()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .render(chunk)
+}
+
+pub struct IhdrCodec;
+
+impl ChunkCodec for IhdrCodec {
+    type Value = Ihdr;
+
+    fn chunk_type(&self) -> ChunkType {
+        ChunkType::from_str(Ihdr::CHUNK_TYPE).expect("IHDR is a valid chunk type")
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<Ihdr> {
+        Ok(Ihdr::try_from(&Chunk::new(
+            self.chunk_type(),
+            data.to_vec(),
+        ))?)
+    }
+
+    fn encode(&self, value: &Ihdr) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+/// Reads and writes one of the tEXt, zTXt and iTXt chunk types.
+pub struct TextCodec {
+    chunk_type: ChunkType,
+}
+
+impl TextCodec {
+    /// Panics if `chunk_type` is not a valid chunk type.
+    pub fn new(chunk_type: &str) -> TextCodec {
+        TextCodec {
+            chunk_type: ChunkType::from_str(chunk_type).expect("text chunk types are valid"),
+        }
+    }
+}
+
+impl ChunkCodec for TextCodec {
+    type Value = TextChunk;
+
+    fn chunk_type(&self) -> ChunkType {
+        self.chunk_type.clone()
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<TextChunk> {
+        Ok(TextChunk::try_from(&Chunk::new(
+            self.chunk_type(),
+            data.to_vec(),
+        ))?)
+    }
+
+    fn encode(&self, value: &TextChunk) -> crate::Result<Vec<u8>> {
+        Ok(value.to_chunk(&self.chunk_type)?.data().to_vec())
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    WrongChunkType(String),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongChunkType(chunk_type) => {
+                write!(f, "Error: codec cannot decode a {} chunk", chunk_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A private chunk holding a big-endian frame counter.
+    struct CounterCodec;
+
+    impl ChunkCodec for CounterCodec {
+        type Value = u32;
+
+        fn chunk_type(&self) -> ChunkType {
+            ChunkType::from_str("cnTr").unwrap()
+        }
+
+        fn decode(&self, data: &[u8]) -> crate::Result<u32> {
+            let bytes: [u8; 4] = data
+                .try_into()
+                .map_err(|e| crate::Error::Parse(Box::new(e)))?;
+            Ok(u32::from_be_bytes(bytes))
+        }
+
+        fn encode(&self, value: &u32) -> crate::Result<Vec<u8>> {
+            Ok(value.to_be_bytes().to_vec())
+        }
+    }
+
+    #[test]
+    fn test_register_private_codec() {
+        let mut registry = CodecRegistry::empty();
+        let chunk = CounterCodec.encode_chunk(&42).unwrap();
+        assert!(registry.render(&chunk).is_none());
+
+        registry.register(CounterCodec);
+        assert_eq!(registry.render(&chunk).unwrap().unwrap(), "42");
+        assert_eq!(CounterCodec.decode_chunk(&chunk).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_default_codecs() {
+        let registry = CodecRegistry::default();
+        let text = TextCodec::new("zTXt")
+            .encode_chunk(&TextChunk::new("Title", "Dice"))
+            .unwrap();
+        assert_eq!(registry.render(&text).unwrap().unwrap(), "Title: \"Dice\"");
+
+        let header = [0, 0, 0, 2, 0, 0, 0, 3, 8, 2, 0, 0, 0];
+        let ihdr = IhdrCodec.decode(&header).unwrap();
+        assert_eq!(IhdrCodec.encode(&ihdr).unwrap(), header);
+    }
+
+    #[test]
+    fn test_decode_wrong_chunk_type() {
+        let chunk = TextCodec::new("tEXt")
+            .encode_chunk(&TextChunk::new("a", "b"))
+            .unwrap();
+        assert!(CounterCodec.decode_chunk(&chunk).is_err());
+    }
+}
@@ -0,0 +1,612 @@
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkCodec;
+use crate::image_data::inflate;
+use crate::png::Png;
+
+const SCALE: f64 = 100_000.0;
+const MAX_PROFILE_NAME_LENGTH: usize = 79;
+
+/// The gAMA and cHRM values that accompany an sRGB chunk for decoders that ignore it.
+pub const SRGB_GAMMA: Gamma = Gamma(45455);
+pub const SRGB_CHROMATICITIES: Chromaticities = Chromaticities {
+    white: (31270, 32900),
+    red: (64000, 33000),
+    green: (30000, 60000),
+    blue: (15000, 6000),
+};
+
+/// The gAMA chunk: the encoding gamma times 100000.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Gamma(u32);
+
+impl Gamma {
+    pub const CHUNK_TYPE: &'static str = "gAMA";
+
+    pub fn new(gamma: f64) -> Gamma {
+        Gamma((gamma * SCALE).round() as u32)
+    }
+
+    pub fn gamma(&self) -> f64 {
+        self.0 as f64 / SCALE
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for Gamma {
+    type Error = ColorError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        match data.len() {
+            4 => Ok(Gamma(read_u32(data, 0))),
+            length => Err(ColorError::InvalidLength(Self::CHUNK_TYPE, length)),
+        }
+    }
+}
+
+impl Display for Gamma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gamma {:.5}", self.gamma())
+    }
+}
+
+/// The cHRM chunk: CIE x and y of the white point and primaries, times 100000.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Chromaticities {
+    white: (u32, u32),
+    red: (u32, u32),
+    green: (u32, u32),
+    blue: (u32, u32),
+}
+
+impl Chromaticities {
+    pub const CHUNK_TYPE: &'static str = "cHRM";
+
+    pub fn white(&self) -> (f64, f64) {
+        scale(self.white)
+    }
+
+    pub fn red(&self) -> (f64, f64) {
+        scale(self.red)
+    }
+
+    pub fn green(&self) -> (f64, f64) {
+        scale(self.green)
+    }
+
+    pub fn blue(&self) -> (f64, f64) {
+        scale(self.blue)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        [self.white, self.red, self.green, self.blue]
+            .iter()
+            .flat_map(|&(x, y)| [x.to_be_bytes(), y.to_be_bytes()].concat())
+            .collect()
+    }
+}
+
+impl TryFrom<&[u8]> for Chromaticities {
+    type Error = ColorError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != 32 {
+            return Err(ColorError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        let point = |index: usize| (read_u32(data, index * 8), read_u32(data, index * 8 + 4));
+        Ok(Chromaticities {
+            white: point(0),
+            red: point(1),
+            green: point(2),
+            blue: point(3),
+        })
+    }
+}
+
+impl Display for Chromaticities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (white, red, green, blue) = (self.white(), self.red(), self.green(), self.blue());
+        write!(
+            f,
+            "white ({:.4}, {:.4}), red ({:.4}, {:.4}), green ({:.4}, {:.4}), blue ({:.4}, {:.4})",
+            white.0, white.1, red.0, red.1, green.0, green.1, blue.0, blue.1
+        )
+    }
+}
+
+/// The sRGB chunk, holding the rendering intent.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Srgb {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl Srgb {
+    pub const CHUNK_TYPE: &'static str = "sRGB";
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+impl TryFrom<&[u8]> for Srgb {
+    type Error = ColorError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        match data {
+            [0] => Ok(Self::Perceptual),
+            [1] => Ok(Self::RelativeColorimetric),
+            [2] => Ok(Self::Saturation),
+            [3] => Ok(Self::AbsoluteColorimetric),
+            [intent] => Err(ColorError::InvalidRenderingIntent(*intent)),
+            _ => Err(ColorError::InvalidLength(Self::CHUNK_TYPE, data.len())),
+        }
+    }
+}
+
+impl Display for Srgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Perceptual => write!(f, "sRGB, perceptual intent"),
+            Self::RelativeColorimetric => write!(f, "sRGB, relative colorimetric intent"),
+            Self::Saturation => write!(f, "sRGB, saturation intent"),
+            Self::AbsoluteColorimetric => write!(f, "sRGB, absolute colorimetric intent"),
+        }
+    }
+}
+
+/// The iCCP chunk, with the profile already inflated.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct IccProfile {
+    name: String,
+    profile: Vec<u8>,
+}
+
+impl IccProfile {
+    pub const CHUNK_TYPE: &'static str = "iCCP";
+
+    pub fn new(name: &str, profile: Vec<u8>) -> Result<IccProfile, ColorError> {
+        let length = name.chars().count();
+        if length == 0 || length > MAX_PROFILE_NAME_LENGTH || name.chars().any(|c| c > '\u{ff}') {
+            return Err(ColorError::InvalidProfileName);
+        }
+        Ok(IccProfile {
+            name: name.to_string(),
+            profile,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The uncompressed ICC profile, as it would be stored in an .icc file.
+    pub fn profile(&self) -> &[u8] {
+        &self.profile
+    }
+
+    /// The data colour space signature from the profile header, such as `RGB` or `GRAY`.
+    pub fn color_space(&self) -> Option<String> {
+        if self.profile.get(36..40) != Some(b"acsp") {
+            return None;
+        }
+        let signature = self.profile.get(16..20)?;
+        Some(String::from_utf8_lossy(signature).trim_end().to_string())
+    }
+
+    /// A guess at whether this is an sRGB profile, based on its description mentioning sRGB.
+    pub fn looks_like_srgb(&self) -> bool {
+        self.profile.windows(4).any(|window| window == b"sRGB")
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.name.chars().map(|c| c as u8).collect();
+        bytes.extend([0, 0]);
+        bytes.extend(compress_to_vec_zlib(&self.profile, 9));
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for IccProfile {
+    type Error = ColorError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let separator = data
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(ColorError::InvalidProfileName)?;
+        let name: String = data[..separator].iter().map(|&b| b as char).collect();
+        let (method, compressed) = data[separator + 1..]
+            .split_first()
+            .ok_or(ColorError::InvalidLength(Self::CHUNK_TYPE, data.len()))?;
+        if *method != 0 {
+            return Err(ColorError::UnknownCompression(*method));
+        }
+        let profile = inflate(compressed).map_err(|e| ColorError::Inflate(e.to_string()))?;
+        IccProfile::new(&name, profile)
+    }
+}
+
+impl Display for IccProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ICC profile {:?}, {} bytes",
+            self.name,
+            self.profile.len()
+        )?;
+        if let Some(color_space) = self.color_space() {
+            write!(f, ", {} data", color_space)?;
+        }
+        Ok(())
+    }
+}
+
+/// The cICP chunk: coding-independent code points as defined by ITU-T H.273.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Cicp {
+    color_primaries: u8,
+    transfer_function: u8,
+    matrix_coefficients: u8,
+    full_range: bool,
+}
+
+impl Cicp {
+    pub const CHUNK_TYPE: &'static str = "cICP";
+
+    pub fn color_primaries(&self) -> u8 {
+        self.color_primaries
+    }
+
+    pub fn transfer_function(&self) -> u8 {
+        self.transfer_function
+    }
+
+    pub fn matrix_coefficients(&self) -> u8 {
+        self.matrix_coefficients
+    }
+
+    pub fn full_range(&self) -> bool {
+        self.full_range
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        vec![
+            self.color_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            self.full_range as u8,
+        ]
+    }
+}
+
+impl TryFrom<&[u8]> for Cicp {
+    type Error = ColorError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        match *data {
+            [color_primaries, transfer_function, 0, full_range @ (0 | 1)] => Ok(Cicp {
+                color_primaries,
+                transfer_function,
+                matrix_coefficients: 0,
+                full_range: full_range == 1,
+            }),
+            [_, _, _, _] => Err(ColorError::InvalidCicp),
+            _ => Err(ColorError::InvalidLength(Self::CHUNK_TYPE, data.len())),
+        }
+    }
+}
+
+impl Display for Cicp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let primaries = match self.color_primaries {
+            1 => "BT.709",
+            9 => "BT.2020",
+            11 => "DCI-P3",
+            12 => "Display P3",
+            _ => "other",
+        };
+        let transfer = match self.transfer_function {
+            1 => "BT.709",
+            8 => "linear",
+            13 => "sRGB",
+            16 => "PQ",
+            18 => "HLG",
+            _ => "other",
+        };
+        write!(
+            f,
+            "cICP primaries {} ({}), transfer {} ({}), {} range",
+            self.color_primaries,
+            primaries,
+            self.transfer_function,
+            transfer,
+            if self.full_range { "full" } else { "narrow" }
+        )
+    }
+}
+
+/// The colour space a decoder should use, following the precedence of the PNG specification.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ColorSpace {
+    Cicp(Cicp),
+    Icc(IccProfile),
+    Srgb(Srgb),
+    Calibrated(Option<Gamma>, Option<Chromaticities>),
+    Unspecified,
+}
+
+impl ColorSpace {
+    /// cICP wins over iCCP, which wins over sRGB, which wins over gAMA and cHRM. Only the
+    /// first chunk of each type counts, as a decoder would read it.
+    pub fn of(png: &Png) -> Result<ColorSpace, ColorError> {
+        let data = |chunk_type: &str| png.chunk_by_type(chunk_type).map(|chunk| chunk.data());
+
+        if let Some(data) = data(Cicp::CHUNK_TYPE) {
+            return Ok(ColorSpace::Cicp(Cicp::try_from(data)?));
+        }
+        if let Some(data) = data(IccProfile::CHUNK_TYPE) {
+            return Ok(ColorSpace::Icc(IccProfile::try_from(data)?));
+        }
+        if let Some(data) = data(Srgb::CHUNK_TYPE) {
+            return Ok(ColorSpace::Srgb(Srgb::try_from(data)?));
+        }
+        let gamma = data(Gamma::CHUNK_TYPE).map(Gamma::try_from).transpose()?;
+        let chromaticities = data(Chromaticities::CHUNK_TYPE)
+            .map(Chromaticities::try_from)
+            .transpose()?;
+        match (gamma, chromaticities) {
+            (None, None) => Ok(ColorSpace::Unspecified),
+            (gamma, chromaticities) => Ok(ColorSpace::Calibrated(gamma, chromaticities)),
+        }
+    }
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cicp(cicp) => write!(f, "{}", cicp),
+            Self::Icc(profile) => write!(f, "{}", profile),
+            Self::Srgb(srgb) => write!(f, "{}", srgb),
+            Self::Calibrated(gamma, chromaticities) => {
+                write!(f, "calibrated")?;
+                if let Some(gamma) = gamma {
+                    write!(f, ", {}", gamma)?;
+                }
+                if let Some(chromaticities) = chromaticities {
+                    write!(f, ", {}", chromaticities)?;
+                }
+                Ok(())
+            }
+            Self::Unspecified => write!(f, "unspecified, viewers will usually assume sRGB"),
+        }
+    }
+}
+
+/// Problems with the colour chunks that make viewers disagree about the image's colours.
+pub fn color_warnings(png: &Png) -> Vec<String> {
+    let count = |chunk_type: &str| {
+        png.chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string() == chunk_type)
+            .count()
+    };
+    let mut warnings = vec![];
+
+    for chunk_type in COLOR_CHUNK_TYPES {
+        if count(chunk_type) > 1 {
+            warnings.push(format!(
+                "{} appears {} times, only the first is used",
+                chunk_type,
+                count(chunk_type)
+            ));
+        }
+    }
+    if count(Srgb::CHUNK_TYPE) > 0 && count(IccProfile::CHUNK_TYPE) > 0 {
+        warnings.push("both sRGB and iCCP are present, they should not appear together".into());
+    }
+    if count(Srgb::CHUNK_TYPE) > 0 {
+        let gamma = png
+            .chunk_by_type(Gamma::CHUNK_TYPE)
+            .and_then(|chunk| Gamma::try_from(chunk.data()).ok());
+        if gamma.is_some_and(|gamma| gamma != SRGB_GAMMA) {
+            warnings.push("gAMA disagrees with sRGB, old decoders will shift colours".into());
+        }
+    }
+
+    warnings
+}
+
+/// The chunk types describing the colour space, which belong before PLTE and IDAT.
+pub const COLOR_CHUNK_TYPES: [&str; 5] = [
+    Gamma::CHUNK_TYPE,
+    Chromaticities::CHUNK_TYPE,
+    Srgb::CHUNK_TYPE,
+    IccProfile::CHUNK_TYPE,
+    Cicp::CHUNK_TYPE,
+];
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn scale((x, y): (u32, u32)) -> (f64, f64) {
+    (x as f64 / SCALE, y as f64 / SCALE)
+}
+
+fn chunk_type(name: &str) -> ChunkType {
+    ChunkType::from_str(name).expect("colour chunk types are valid")
+}
+
+/// Implements `ChunkCodec` for a colour chunk model with `TryFrom<&[u8]>` and `as_bytes`.
+macro_rules! color_codec {
+    ($codec:ident, $value:ty) => {
+        pub struct $codec;
+
+        impl ChunkCodec for $codec {
+            type Value = $value;
+
+            fn chunk_type(&self) -> ChunkType {
+                chunk_type(<$value>::CHUNK_TYPE)
+            }
+
+            fn decode(&self, data: &[u8]) -> crate::Result<$value> {
+                Ok(<$value>::try_from(data)?)
+            }
+
+            fn encode(&self, value: &$value) -> crate::Result<Vec<u8>> {
+                Ok(value.as_bytes())
+            }
+        }
+    };
+}
+
+color_codec!(GammaCodec, Gamma);
+color_codec!(ChromaticitiesCodec, Chromaticities);
+color_codec!(SrgbCodec, Srgb);
+color_codec!(IccProfileCodec, IccProfile);
+color_codec!(CicpCodec, Cicp);
+
+#[derive(Debug)]
+pub enum ColorError {
+    InvalidLength(&'static str, usize),
+    InvalidRenderingIntent(u8),
+    InvalidProfileName,
+    UnknownCompression(u8),
+    Inflate(String),
+    InvalidCicp,
+}
+
+impl Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(chunk_type, length) => {
+                write!(
+                    f,
+                    "Error: {} chunk of invalid length {}",
+                    chunk_type, length
+                )
+            }
+            Self::InvalidRenderingIntent(intent) => {
+                write!(f, "Error: invalid sRGB rendering intent {}", intent)
+            }
+            Self::InvalidProfileName => write!(
+                f,
+                "Error: ICC profile name must be 1 to 79 Latin-1 characters"
+            ),
+            Self::UnknownCompression(method) => {
+                write!(f, "Error: unknown iCCP compression method {}", method)
+            }
+            Self::Inflate(inflate_error) => write!(f, "Error: {}", inflate_error),
+            Self::InvalidCicp => write!(
+                f,
+                "Error: cICP must use matrix coefficients 0 and a range flag of 0 or 1"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+
+    fn png(chunks: Vec<(&str, Vec<u8>)>) -> Png {
+        Png::from_chunks(
+            chunks
+                .into_iter()
+                .map(|(name, data)| Chunk::new(chunk_type(name), data))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let gamma = Gamma::try_from(&[0, 0, 177, 143][..]).unwrap();
+        assert_eq!(gamma, SRGB_GAMMA);
+        assert_eq!(format!("{}", gamma), "gamma 0.45455");
+
+        let bytes = SRGB_CHROMATICITIES.as_bytes();
+        assert_eq!(
+            Chromaticities::try_from(&bytes[..]).unwrap(),
+            SRGB_CHROMATICITIES
+        );
+
+        assert_eq!(
+            Srgb::try_from(&[1][..]).unwrap(),
+            Srgb::RelativeColorimetric
+        );
+        assert!(Srgb::try_from(&[4][..]).is_err());
+
+        let cicp = Cicp::try_from(&[9, 16, 0, 1][..]).unwrap();
+        assert_eq!(cicp.as_bytes(), [9, 16, 0, 1]);
+        assert!(Cicp::try_from(&[9, 16, 1, 1][..]).is_err());
+    }
+
+    #[test]
+    fn test_icc_profile() {
+        let mut header = vec![0; 16];
+        header.extend(b"RGB XYZ ");
+        header.resize(36, 0);
+        header.extend(b"acspsRGB IEC61966-2.1");
+        let profile = IccProfile::new("sRGB", header.clone()).unwrap();
+
+        let decoded = IccProfile::try_from(profile.as_bytes().as_slice()).unwrap();
+        assert_eq!(decoded.profile(), header);
+        assert_eq!(decoded.color_space().as_deref(), Some("RGB"));
+        assert!(decoded.looks_like_srgb());
+        assert!(IccProfile::new("", vec![]).is_err());
+    }
+
+    #[test]
+    fn test_color_space_precedence() {
+        let srgb = ("sRGB", vec![0]);
+        let gamma = ("gAMA", vec![0, 0, 0x9c, 0x40]);
+        let cicp = ("cICP", vec![1, 13, 0, 1]);
+
+        assert_eq!(
+            ColorSpace::of(&png(vec![gamma.clone(), srgb.clone()])).unwrap(),
+            ColorSpace::Srgb(Srgb::Perceptual)
+        );
+        assert!(matches!(
+            ColorSpace::of(&png(vec![srgb, cicp])).unwrap(),
+            ColorSpace::Cicp(_)
+        ));
+        assert_eq!(
+            ColorSpace::of(&png(vec![gamma])).unwrap(),
+            ColorSpace::Calibrated(Some(Gamma::new(0.4)), None)
+        );
+        assert_eq!(
+            ColorSpace::of(&png(vec![])).unwrap(),
+            ColorSpace::Unspecified
+        );
+    }
+
+    #[test]
+    fn test_color_warnings() {
+        let chunks = vec![
+            ("sRGB", vec![0]),
+            ("gAMA", vec![0, 0, 0x9c, 0x40]),
+            ("gAMA", vec![0, 0, 0x9c, 0x40]),
+        ];
+        let warnings = color_warnings(&png(chunks));
+        assert_eq!(warnings.len(), 2);
+        assert!(color_warnings(&png(vec![("sRGB", vec![0])])).is_empty());
+    }
+}
@@ -1 +1,1223 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
 
+use crate::apng::{Apng, APNG_CHUNK_TYPES};
+use crate::args::{Action, Arguments, Placement};
+use crate::atomic;
+use crate::capacity;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec;
+use crate::color::{
+    color_warnings, Chromaticities, ColorSpace, Gamma, IccProfile, Srgb, COLOR_CHUNK_TYPES,
+    SRGB_CHROMATICITIES, SRGB_GAMMA,
+};
+use crate::diagnostic::Diagnostic;
+use crate::diff::ChunkDiff;
+use crate::editor::{EditError, Editor, Step};
+use crate::error::Error;
+use crate::exif::Exif;
+use crate::hex::HexDump;
+use crate::palette::PaletteChunks;
+use crate::physical::{
+    ModificationTime, Offsets, PhysicalError, PhysicalScale, PixelDimensions, SignificantBits,
+};
+use crate::png::Png;
+use crate::registry;
+use crate::scan;
+
+/// Stands for stdin when given as an input path and for stdout as an output path.
+pub const STDIO_PATH: &str = "-";
+
+pub fn run(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> crate::Result<()> {
+    match arguments.action() {
+        Action::Encode => encode(arguments, input, out, log),
+        Action::Decode => decode(arguments, input, out, log),
+        Action::Remove => remove(arguments, input, out, log),
+        Action::Print => print(arguments, input, out, log),
+        Action::Extract => extract(arguments, input, out, log),
+        Action::Insert => insert(arguments, input, out, log),
+        Action::Transplant => transplant(arguments, input, out, log),
+        Action::Strip => strip(arguments, input, out, log),
+        Action::Scan => scan(arguments, input, out, log),
+        Action::Diff => diff(arguments, input, out, log),
+        Action::Edit => edit(arguments, input, out, log),
+        Action::Show => show(arguments, input, out, log),
+        Action::Frames => frames(arguments, input, out, log),
+        Action::Inspect => inspect(arguments, input, out, log),
+        Action::Profile => profile(arguments, input, out, log),
+        Action::Set => set(arguments, input, out, log),
+        Action::Exif => exif(arguments, input, out, log),
+        Action::Capacity => capacity(arguments, input, out, log),
+        Action::Run => run_steps(arguments, input, out, log),
+    }
+}
+
+pub fn encode(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let original = read_png(arguments.file_path(), input)?;
+    let mut png = original.clone();
+    let chunk_type = ChunkType::from_str(required(arguments.chunk_type(), "CHUNK_TYPE")?)?;
+    let message = match (arguments.message(), arguments.message_file()) {
+        (Some(message), _) => message.as_bytes().to_vec(),
+        (None, Some(message_file)) if !is_stdio(message_file) => read_bytes(message_file, input)?,
+        (None, _) if is_stdio(arguments.file_path()) => {
+            return Err(Error::usage(
+                "MESSAGE is required when the PNG is read from stdin",
+            ))
+        }
+        (None, _) => read_bytes(Path::new(STDIO_PATH), input)?,
+    };
+
+    if APNG_CHUNK_TYPES.contains(&chunk_type.to_string().as_str()) {
+        return Err(Error::usage(format!(
+            "{} chunks carry APNG sequence numbers and cannot be encoded",
+            chunk_type.to_string()
+        )));
+    }
+    check_message_chunk_type(&chunk_type, arguments.force())?;
+
+    let chunk = Chunk::new(chunk_type, message);
+    match arguments.frame() {
+        Some(frame) => {
+            let index = Apng::try_from(&png)?.frame(frame)?.insert_position();
+            png.insert_chunk(index, chunk);
+        }
+        None => match arguments.placement() {
+            Placement::End => png.append_chunk(chunk),
+            Placement::AfterHeader => png.insert_chunk(1, chunk),
+            Placement::BeforeData => match png.position_of("IDAT") {
+                Some(index) => png.insert_chunk(index, chunk),
+                None => png.append_chunk(chunk),
+            },
+        },
+    }
+
+    write_png(&original, png, output_path(arguments), arguments, out)
+}
+
+pub fn decode(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+    let chunk_type = required(arguments.chunk_type(), "CHUNK_TYPE")?;
+
+    let chunk = match arguments.frame() {
+        Some(frame) => {
+            let span = Apng::try_from(&png)?.frame(frame)?.span();
+            png.chunks()[span]
+                .iter()
+                .find(|chunk| chunk.chunk_type().to_string() == chunk_type)
+        }
+        None => png.chunk_by_type(chunk_type),
+    }
+    .ok_or_else(|| Error::not_found(format!("No chunk of type {} found", chunk_type)))?;
+    out.write_all(chunk.data())?;
+
+    Ok(())
+}
+
+pub fn remove(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let original = read_png(arguments.file_path(), input)?;
+    let mut png = original.clone();
+    let chunk_type = required(arguments.chunk_type(), "CHUNK_TYPE")?;
+
+    png.remove_chunk(chunk_type)?;
+
+    write_png(&original, png, output_path(arguments), arguments, out)
+}
+
+pub fn print(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+    let (offset, length) = arguments.hex_range();
+
+    for chunk in png.chunks() {
+        match arguments.hex() {
+            true => write!(
+                out,
+                "{}\n{}",
+                chunk.summary(),
+                HexDump::new(chunk.data()).range(offset, length)
+            )?,
+            false => {
+                writeln!(out, "{}", chunk.summary())?;
+                match codec::render(chunk) {
+                    Some(Ok(value)) => writeln!(out, "    {}", value)?,
+                    Some(Err(error)) => writeln!(out, "    Error: {}", error.report())?,
+                    None => {}
+                }
+            }
+        }
+    }
+    if !png.trailer().is_empty() {
+        writeln!(out, "Trailer: {} bytes after IEND", png.trailer().len())?;
+    }
+
+    Ok(())
+}
+
+/// Describes every chunk of the given type and what the registry knows about it, followed by
+/// its decoded value or, with `--hex`, a hex dump of the window selected by `--offset` and
+/// `--length`.
+pub fn show(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+    let chunk_type = required(arguments.chunk_type(), "CHUNK_TYPE")?;
+    let (offset, length) = arguments.hex_range();
+
+    let chunks: Vec<&Chunk> = png
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().to_string() == chunk_type)
+        .collect();
+    if chunks.is_empty() {
+        return Err(Error::not_found(format!(
+            "No chunk of type {} found",
+            chunk_type
+        )));
+    }
+
+    for chunk in chunks {
+        writeln!(out, "{}", chunk.summary())?;
+        if let Some(info) = registry::lookup(chunk_type) {
+            writeln!(out, "{}", info)?;
+        }
+        if arguments.hex() {
+            write!(out, "{}", HexDump::new(chunk.data()).range(offset, length))?;
+        } else if let Some(value) = codec::render(chunk) {
+            writeln!(out, "{}", value?)?;
+        } else if let Ok(text) = chunk.data_as_string() {
+            writeln!(out, "{}", text)?;
+        } else {
+            writeln!(out, "Binary data, use --hex to dump it")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the frames of an APNG, or with `--frame` writes one frame to OUTPUT_PATH as a PNG.
+pub fn frames(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+    let apng = Apng::try_from(&png)?;
+
+    if let Some(frame) = arguments.frame() {
+        let path = arguments
+            .output_path()
+            .ok_or_else(|| Error::usage("Missing OUTPUT_PATH to write the frame to, use -o"))?;
+        return write_bytes(path, &apng.frame_png(frame)?.as_bytes(), arguments, out);
+    }
+
+    writeln!(out, "{}", apng.control())?;
+    for (index, frame) in apng.frames().iter().enumerate() {
+        writeln!(out, "  #{} {}", index, frame)?;
+    }
+
+    Ok(())
+}
+
+/// Summarises the image header, the colour space a viewer will use, the physical metadata
+/// and the palette chunks, with warnings about chunks that are duplicated, contradict each
+/// other or do not fit the header.
+pub fn inspect(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+
+    writeln!(out, "Image: {}", png.ihdr()?)?;
+    writeln!(out, "Color space: {}", ColorSpace::of(&png)?)?;
+    for chunk in png.chunks() {
+        if COLOR_CHUNK_TYPES.contains(&chunk.chunk_type().to_string().as_str()) {
+            writeln!(out, "  {}", chunk.summary())?;
+        }
+    }
+    for warning in color_warnings(&png) {
+        writeln!(out, "Warning: {}", warning)?;
+    }
+
+    let data = |chunk_type: &str| png.chunk_by_type(chunk_type).map(|chunk| chunk.data());
+    if let Some(data) = data(SignificantBits::CHUNK_TYPE) {
+        match SignificantBits::decode(data, &png.ihdr()?) {
+            Ok(bits) => writeln!(out, "Precision: {}", bits)?,
+            Err(physical_error) => writeln!(out, "{}", physical_error)?,
+        }
+    }
+    if let Some(data) = data(PixelDimensions::CHUNK_TYPE) {
+        write_field(out, "Density", PixelDimensions::try_from(data))?;
+    }
+    if let Some(data) = data(PhysicalScale::CHUNK_TYPE) {
+        write_field(out, "Scale", PhysicalScale::try_from(data))?;
+    }
+    if let Some(data) = data(Offsets::CHUNK_TYPE) {
+        write_field(out, "Position", Offsets::try_from(data))?;
+    }
+    if let Some(data) = data(ModificationTime::CHUNK_TYPE) {
+        write_field(out, "Modified", ModificationTime::try_from(data))?;
+    }
+
+    match PaletteChunks::of(&png) {
+        Ok(chunks) => {
+            if let Some(palette) = chunks.palette() {
+                writeln!(out, "Palette: {}", palette)?;
+            }
+            if let Some(transparency) = chunks.transparency() {
+                writeln!(out, "Transparency: {}", transparency)?;
+            }
+            if let Some(background) = chunks.background() {
+                writeln!(out, "Background: {}", background)?;
+            }
+            if let Some(histogram) = chunks.histogram() {
+                writeln!(out, "Histogram: {}", histogram)?;
+            }
+            for suggested in chunks.suggested() {
+                writeln!(out, "Suggested palette: {}", suggested)?;
+            }
+        }
+        Err(palette_error) => writeln!(out, "{}", palette_error)?,
+    }
+
+    Ok(())
+}
+
+/// Writes a decoded chunk after its label, or the reason it could not be decoded.
+fn write_field<T: Display>(
+    out: &mut dyn Write,
+    label: &str,
+    decoded: Result<T, PhysicalError>,
+) -> io::Result<()> {
+    match decoded {
+        Ok(value) => writeln!(out, "{}: {}", label, value),
+        Err(physical_error) => writeln!(out, "{}", physical_error),
+    }
+}
+
+/// Writes the inflated ICC profile to OUTPUT_PATH or stdout. With `--to-srgb` the profile is
+/// replaced by sRGB, gAMA and cHRM chunks, and with `--embed` an sRGB declaration is replaced
+/// by the given profile.
+pub fn profile(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let original = read_png(arguments.file_path(), input)?;
+    let mut png = original.clone();
+
+    let chunk = |chunk_type: &str, data: Vec<u8>| -> crate::Result<Chunk> {
+        Ok(Chunk::new(ChunkType::from_str(chunk_type)?, data))
+    };
+    let mut replace = |remove: &[&str], add: Vec<Chunk>| {
+        for chunk_type in remove {
+            png.remove_chunks_by_type(chunk_type);
+        }
+        let position = ["PLTE", "IDAT"]
+            .iter()
+            .filter_map(|chunk_type| png.position_of(chunk_type))
+            .min()
+            .unwrap_or(png.chunks().len());
+        for (offset, chunk) in add.into_iter().enumerate() {
+            png.insert_chunk(position + offset, chunk);
+        }
+    };
+
+    match (arguments.to_srgb(), arguments.embed()) {
+        (true, Some(_)) => return Err(Error::usage("Use either --to-srgb or --embed, not both")),
+        (true, None) => {
+            if let Some(existing) = original.chunk_by_type(IccProfile::CHUNK_TYPE) {
+                let existing = IccProfile::try_from(existing.data())?;
+                if !existing.looks_like_srgb() && !arguments.force() {
+                    return Err(Error::usage(format!(
+                        "{} does not look like sRGB, converting would shift colours, use --force",
+                        existing
+                    )));
+                }
+            }
+            replace(
+                &[
+                    IccProfile::CHUNK_TYPE,
+                    Srgb::CHUNK_TYPE,
+                    Gamma::CHUNK_TYPE,
+                    Chromaticities::CHUNK_TYPE,
+                ],
+                vec![
+                    chunk(Srgb::CHUNK_TYPE, Srgb::Perceptual.as_bytes())?,
+                    chunk(Gamma::CHUNK_TYPE, SRGB_GAMMA.as_bytes())?,
+                    chunk(Chromaticities::CHUNK_TYPE, SRGB_CHROMATICITIES.as_bytes())?,
+                ],
+            );
+        }
+        (false, Some(path)) => {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "ICC profile".to_string());
+            let bytes = fs::read(path).map_err(|io_error| Error::file(path, io_error))?;
+            let profile = IccProfile::new(&name, bytes)?;
+            replace(
+                &[IccProfile::CHUNK_TYPE, Srgb::CHUNK_TYPE],
+                vec![chunk(IccProfile::CHUNK_TYPE, profile.as_bytes())?],
+            );
+        }
+        (false, None) => {
+            let chunk = original
+                .chunk_by_type(IccProfile::CHUNK_TYPE)
+                .ok_or_else(|| {
+                    Error::not_found("No iCCP chunk found, the image has no embedded profile")
+                })?;
+            let profile = IccProfile::try_from(chunk.data())?;
+            return match arguments.output_path() {
+                Some(path) => write_bytes(path, profile.profile(), arguments, out),
+                None => Ok(out.write_all(profile.profile())?),
+            };
+        }
+    }
+
+    write_png(&original, png, output_path(arguments), arguments, out)
+}
+
+/// Rewrites the file with only the changes asked for by `--dpi` and `--touch`, which every
+/// command that writes a PNG applies as well.
+pub fn set(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+    write_png(&png, png.clone(), output_path(arguments), arguments, out)
+}
+
+/// Performs the steps of the selected profile in order, each one reading what the previous
+/// one wrote. A dry run previews every step against the input file instead.
+pub fn run_steps(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> crate::Result<()> {
+    if is_stdio(arguments.file_path()) || is_stdio(output_path(arguments)) {
+        return Err(Error::usage(
+            "run reads and writes files, not stdin or stdout",
+        ));
+    }
+    for (index, action) in arguments.steps().iter().enumerate() {
+        let file_path = if index == 0 || arguments.dry_run() {
+            arguments.file_path()
+        } else {
+            output_path(arguments)
+        };
+        let step = arguments.for_step(index, *action, file_path.to_path_buf());
+        Arguments::action_has_enough_data(&step)?;
+        run(&step, input, out, log)?;
+    }
+    Ok(())
+}
+
+/// Lists the Exif tags, or with `--remove-gps` and `--orientation` edits them in place and
+/// writes the file.
+pub fn exif(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> crate::Result<()> {
+    let original = read_png(arguments.file_path(), input)?;
+    let position = original
+        .position_of(Exif::CHUNK_TYPE)
+        .ok_or_else(|| Error::not_found("No eXIf chunk found"))?;
+    let mut exif = Exif::try_from(original.chunks()[position].data())?;
+
+    if !arguments.remove_gps() && arguments.orientation().is_none() {
+        for entry in exif.entries()? {
+            writeln!(out, "{}", entry)?;
+        }
+        return Ok(());
+    }
+
+    if arguments.remove_gps() && !exif.remove_gps()? {
+        writeln!(log, "No GPS tags found")?;
+    }
+    if let Some(orientation) = arguments.orientation() {
+        exif.set_orientation(orientation)?;
+    }
+    let mut png = original.clone();
+    let chunk = Chunk::new(
+        ChunkType::from_str(Exif::CHUNK_TYPE)?,
+        exif.as_bytes().to_vec(),
+    );
+    png.replace_chunk(position, chunk);
+    write_png(&original, png, output_path(arguments), arguments, out)
+}
+
+/// Reports how many bytes each hiding method can carry in the image.
+pub fn capacity(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+
+    for capacity in capacity::capacities(&png.ihdr()?) {
+        writeln!(out, "{}", capacity)?;
+    }
+
+    Ok(())
+}
+
+/// Dumps a chunk's data, or the whole framed chunk with `--framed`, to OUTPUT_PATH or stdout.
+pub fn extract(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let png = read_png(arguments.file_path(), input)?;
+    let chunk_type = required(arguments.chunk_type(), "CHUNK_TYPE")?;
+
+    let chunk = png
+        .chunk_by_type(chunk_type)
+        .ok_or_else(|| Error::not_found(format!("No chunk of type {} found", chunk_type)))?;
+    let bytes = match arguments.framed() {
+        true => chunk.as_bytes(),
+        false => chunk.data().to_vec(),
+    };
+
+    match arguments.output_path() {
+        Some(path) => write_bytes(path, &bytes, arguments, out)?,
+        None => out.write_all(&bytes)?,
+    }
+
+    Ok(())
+}
+
+/// Inserts a chunk read from a file.
+///
+/// With two arguments (`insert <file> <chunk.bin>`) the file must hold exactly one framed
+/// chunk, as written by `extract --framed`. With three (`insert <file> <TYPE> <data.bin>`)
+/// the file holds raw data which is wrapped in a new chunk of the given type.
+pub fn insert(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let original = read_png(arguments.file_path(), input)?;
+    let mut png = original.clone();
+    let first = required(arguments.chunk_type(), "CHUNK_TYPE")?;
+
+    let chunk = match arguments.message() {
+        Some(data_path) => Chunk::new(
+            ChunkType::from_str(first)?,
+            read_bytes(Path::new(data_path), input)?,
+        ),
+        None => Chunk::from_framed(&read_bytes(Path::new(first), input)?)?,
+    };
+    png.append_chunk(chunk);
+
+    write_png(&original, png, output_path(arguments), arguments, out)
+}
+
+/// Copies ancillary chunks from FILE_PATH into the PNG given as the second argument.
+///
+/// Chunks of a transplanted type already present in the destination are replaced. Chunks
+/// found before IDAT in the source are placed before IDAT in the destination, the rest
+/// before IEND. Unsafe-to-copy chunks are skipped when the critical chunks of the two
+/// images differ, unless `--force` is given.
+pub fn transplant(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> crate::Result<()> {
+    let source = read_png(arguments.file_path(), input)?;
+    let destination_path = Path::new(required(arguments.chunk_type(), "DESTINATION")?);
+    let original = read_png(destination_path, input)?;
+    let mut destination = original.clone();
+
+    let critical_match = critical_bytes(&source) == critical_bytes(&destination);
+    let first_data = source
+        .position_of(Png::DATA_CHUNK_TYPE)
+        .unwrap_or(source.chunks().len());
+
+    let mut before_data = vec![];
+    let mut after_data = vec![];
+    for (index, chunk) in source.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        if chunk_type.is_critical() {
+            continue;
+        }
+        if !arguments.only().is_empty() && !arguments.only().contains(&chunk_type.to_string()) {
+            continue;
+        }
+        if !chunk_type.is_safe_to_copy() && !critical_match && !arguments.force() {
+            writeln!(
+                log,
+                "Skipped {}: unsafe to copy and critical chunks differ",
+                chunk_type.to_string()
+            )?;
+            continue;
+        }
+        match index < first_data {
+            true => before_data.push(chunk.clone()),
+            false => after_data.push(chunk.clone()),
+        }
+    }
+
+    for chunk in before_data.iter().chain(after_data.iter()) {
+        destination.remove_chunks_by_type(&chunk.chunk_type().to_string());
+    }
+
+    for chunk in before_data {
+        let chunk_type = chunk.chunk_type().to_string();
+        writeln!(log, "Copied {} ({} bytes)", chunk_type, chunk.length())?;
+        let index = position_before_data(&destination, &chunk_type);
+        destination.insert_chunk(index, chunk);
+    }
+    for chunk in after_data {
+        writeln!(
+            log,
+            "Copied {} ({} bytes)",
+            chunk.chunk_type().to_string(),
+            chunk.length()
+        )?;
+        destination.append_chunk(chunk);
+    }
+    destination.validate_structure()?;
+
+    write_png(
+        &original,
+        destination,
+        arguments.output_path().unwrap_or(destination_path),
+        arguments,
+        out,
+    )
+}
+
+/// Removes ancillary chunks so the image can be published without its metadata.
+///
+/// Every ancillary chunk is removed unless it is listed with `--keep`; `--only` narrows
+/// removal to the listed types. Critical chunks are never touched so the image stays
+/// renderable, which means unknown private chunks go too unless explicitly kept.
+pub fn strip(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> crate::Result<()> {
+    let original = read_png(arguments.file_path(), input)?;
+    let mut png = original.clone();
+
+    let removed = png.remove_chunks_where(|chunk| {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk.chunk_type().is_critical() || arguments.keep().contains(&chunk_type) {
+            return false;
+        }
+        arguments.only().is_empty() || arguments.only().contains(&chunk_type)
+    });
+
+    let verb = match arguments.dry_run() {
+        true => "Would remove",
+        false => "Removed",
+    };
+    for chunk in removed.iter() {
+        let visibility = match chunk.chunk_type().is_public() {
+            true => "public",
+            false => "private",
+        };
+        let name = chunk.chunk_type().to_string();
+        let description = registry::lookup(&name).map_or("unregistered", |info| info.description());
+        writeln!(
+            log,
+            "{} {} ({}, {} bytes): {}",
+            verb,
+            name,
+            visibility,
+            chunk.length(),
+            description
+        )?;
+    }
+    let removed_bytes: usize = removed.iter().map(|chunk| chunk.as_bytes().len()).sum();
+    writeln!(
+        log,
+        "{} {} chunks, {} bytes",
+        verb,
+        removed.len(),
+        removed_bytes
+    )?;
+
+    write_png(&original, png, output_path(arguments), arguments, out)
+}
+
+/// Prints a risk report of the places where data may be hidden in the file.
+pub fn scan(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let bytes = read_bytes(arguments.file_path(), input)?;
+    let report = scan::scan(&bytes)?;
+
+    writeln!(out, "{}", arguments.file_path().display())?;
+    for finding in report.findings() {
+        writeln!(out, "  [{}] {}", finding.risk(), finding)?;
+    }
+    match report.lsb_probability() {
+        Some(probability) => writeln!(out, "  LSB embedding probability: {:.3}", probability)?,
+        None => writeln!(out, "  LSB embedding probability: not analysed")?,
+    }
+    writeln!(out, "  Risk: {}", report.risk())?;
+
+    Ok(())
+}
+
+/// Compares FILE_PATH with the PNG given as the second argument, chunk by chunk.
+///
+/// With `--critical-only` ancillary chunks are left out of the comparison. Returns
+/// [`Error::Differs`] when the compared chunks or trailers are not identical.
+pub fn diff(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    _log: &mut dyn Write,
+) -> crate::Result<()> {
+    let before = read_png(arguments.file_path(), input)?;
+    let after = read_png(
+        Path::new(required(arguments.chunk_type(), "SECOND_FILE")?),
+        input,
+    )?;
+
+    let compared = |png: &Png| -> Vec<Chunk> {
+        png.chunks()
+            .iter()
+            .filter(|chunk| !arguments.critical_only() || chunk.chunk_type().is_critical())
+            .cloned()
+            .collect()
+    };
+    let (before_chunks, after_chunks) = (compared(&before), compared(&after));
+    let chunk_diff = ChunkDiff::new(&before_chunks, &after_chunks);
+
+    writeln!(out, "{}", chunk_diff)?;
+    let trailer_differs = !arguments.critical_only() && before.trailer() != after.trailer();
+    if trailer_differs {
+        writeln!(
+            out,
+            "  trailer: {} -> {} bytes",
+            before.trailer().len(),
+            after.trailer().len()
+        )?;
+    }
+
+    match chunk_diff.is_empty() && !trailer_differs {
+        true => Ok(()),
+        false => Err(Error::Differs),
+    }
+}
+
+/// Runs an interactive shell editing the chunks of FILE_PATH, reading commands from stdin.
+///
+/// Errors from a command are reported to `log` and the shell carries on. `write` saves to
+/// the given path, or to OUTPUT_PATH or FILE_PATH, once the structure has been validated.
+pub fn edit(
+    arguments: &Arguments,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> crate::Result<()> {
+    if is_stdio(arguments.file_path()) {
+        return Err(Error::usage(
+            "edit reads its commands from stdin, so FILE_PATH cannot be -",
+        ));
+    }
+    let mut original = read_png(arguments.file_path(), input)?;
+    let mut editor = Editor::new(original.clone());
+    writeln!(
+        out,
+        "Editing {}, type help to list commands",
+        arguments.file_path().display()
+    )?;
+
+    let mut lines = BufReader::new(input).lines();
+    loop {
+        write!(out, "pngme> ")?;
+        out.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            if editor.has_unsaved_changes() {
+                writeln!(log, "{}", EditError::DiscardedChanges)?;
+            }
+            break;
+        };
+
+        match editor.execute(&line, out) {
+            Ok(Step::Continue) => {}
+            Ok(Step::Write(path)) => {
+                let path = path.as_deref().map_or(output_path(arguments), Path::new);
+                write_png(&original, editor.png().clone(), path, arguments, out)?;
+                if !arguments.dry_run() {
+                    writeln!(out, "Wrote {}", path.display())?;
+                    original = editor.png().clone();
+                    editor.mark_saved();
+                }
+            }
+            Ok(Step::Quit) => break,
+            Err(error) => writeln!(log, "{}", error)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the first chunk of the same type, dropping any others, or inserts the chunk just
+/// before the first `before` chunk.
+fn set_chunk(png: &mut Png, chunk: Chunk, before: &str) {
+    let chunk_type = chunk.chunk_type().to_string();
+    let position = png
+        .position_of(&chunk_type)
+        .or_else(|| png.position_of(before))
+        .unwrap_or(png.chunks().len());
+    png.remove_chunks_by_type(&chunk_type);
+    png.insert_chunk(position, chunk);
+}
+
+/// Where a chunk that belongs before the image data goes: before the first chunk the registry
+/// says it must precede, such as PLTE for gAMA, else just before IDAT. Chunks inserted one
+/// after the other at the same place keep their order.
+fn position_before_data(png: &Png, chunk_type: &str) -> usize {
+    let must_precede = registry::lookup(chunk_type).map_or(&[][..], |info| info.must_precede());
+    must_precede
+        .iter()
+        .chain([Png::DATA_CHUNK_TYPE].iter())
+        .filter_map(|other| png.position_of(other))
+        .min()
+        .unwrap_or(png.chunks().len())
+}
+
+/// Messages belong in ancillary, private, safe-to-copy chunks, which decoders skip, no
+/// registered type clashes with and editors keep. `force` allows the other kinds, but never
+/// an invalid reserved bit.
+fn check_message_chunk_type(chunk_type: &ChunkType, force: bool) -> crate::Result<()> {
+    let name = chunk_type.to_string();
+    if !chunk_type.is_reserved_bit_valid() {
+        return Err(Error::usage(format!(
+            "{} has its reserved bit set, the third letter must be uppercase",
+            name
+        )));
+    }
+    let problems: Vec<&str> = [
+        (
+            chunk_type.is_critical(),
+            "critical, so decoders will reject the image",
+        ),
+        (
+            chunk_type.is_public(),
+            "public, so it can clash with a registered chunk type",
+        ),
+        (
+            !chunk_type.is_safe_to_copy(),
+            "unsafe to copy, so editors will drop it",
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(problem, reason)| problem.then_some(reason))
+    .collect();
+    if problems.is_empty() || force {
+        return Ok(());
+    }
+    Err(Error::usage(format!(
+        "{} is {}; use --force to encode anyway",
+        name,
+        problems.join(", ")
+    )))
+}
+
+fn critical_bytes(png: &Png) -> Vec<u8> {
+    png.chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().is_critical())
+        .flat_map(|chunk| chunk.as_bytes())
+        .collect()
+}
+
+fn required<'a>(value: Option<&'a str>, name: &str) -> crate::Result<&'a str> {
+    value.ok_or_else(|| Error::usage(format!("Missing {} from your argument list", name)))
+}
+
+fn output_path(arguments: &Arguments) -> &Path {
+    arguments.output_path().unwrap_or(arguments.file_path())
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO_PATH)
+}
+
+/// Reads the file at `path`, or everything left in `input` when the path is `-`.
+fn read_bytes(path: &Path, input: &mut dyn Read) -> crate::Result<Vec<u8>> {
+    if is_stdio(path) {
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+    fs::read(path).map_err(|io_error| Error::file(path, io_error))
+}
+
+fn write_bytes(
+    path: &Path,
+    bytes: &[u8],
+    arguments: &Arguments,
+    out: &mut dyn Write,
+) -> crate::Result<()> {
+    match is_stdio(path) {
+        true => out.write_all(bytes)?,
+        false => atomic::write_atomic(path, bytes, arguments.backup())
+            .map_err(|io_error| Error::file(path, io_error))?,
+    }
+    Ok(())
+}
+
+fn read_png(path: &Path, input: &mut dyn Read) -> crate::Result<Png> {
+    let bytes = read_bytes(path, input)?;
+    Png::try_from(bytes.as_ref()).map_err(|png_error| {
+        let file_name = match is_stdio(path) {
+            true => "<stdin>".to_string(),
+            false => path.display().to_string(),
+        };
+        match Diagnostic::new(&file_name, &bytes, png_error) {
+            Ok(diagnostic) => diagnostic.into(),
+            Err(png_error) => png_error.into(),
+        }
+    })
+}
+
+/// Writes `png` to `path`, or with `--dry-run` only reports how it differs from `original`.
+fn write_png(
+    original: &Png,
+    mut png: Png,
+    path: &Path,
+    arguments: &Arguments,
+    out: &mut dyn Write,
+) -> crate::Result<()> {
+    if arguments.strip_trailer() {
+        png.strip_trailer();
+    }
+    if let Some(dpi) = arguments.dpi() {
+        let dimensions = PixelDimensions::from_dpi(dpi)?;
+        let chunk = Chunk::new(
+            ChunkType::from_str(PixelDimensions::CHUNK_TYPE)?,
+            dimensions.as_bytes(),
+        );
+        set_chunk(&mut png, chunk, Png::DATA_CHUNK_TYPE);
+    }
+    if arguments.touch() {
+        let time = ModificationTime::now();
+        let chunk = Chunk::new(
+            ChunkType::from_str(ModificationTime::CHUNK_TYPE)?,
+            time.as_bytes(),
+        );
+        set_chunk(&mut png, chunk, Png::END_CHUNK_TYPE);
+    }
+
+    if arguments.dry_run() {
+        writeln!(out, "Dry run, {} not written", path.display())?;
+        writeln!(out, "{}", ChunkDiff::new(original.chunks(), png.chunks()))?;
+        if original.trailer() != png.trailer() {
+            writeln!(
+                out,
+                "  trailer: {} -> {} bytes",
+                original.trailer().len(),
+                png.trailer().len()
+            )?;
+        }
+        return Ok(());
+    }
+
+    write_bytes(path, &png.as_bytes(), arguments, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::testing::chunk;
+    use std::path::PathBuf;
+
+    const RGB_HEADER: [u8; 13] = [0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0];
+    const INDEXED_HEADER: [u8; 13] = [0, 0, 0, 1, 0, 0, 0, 1, 8, 3, 0, 0, 0];
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("pngme-commands-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn write_file(path: &Path, chunks: Vec<Chunk>) -> String {
+        fs::write(path, Png::from_chunks(chunks).as_bytes()).unwrap();
+        path.display().to_string()
+    }
+
+    fn types(path: &Path) -> Vec<String> {
+        read_png(path, &mut std::io::empty())
+            .unwrap()
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    /// Runs a command line, returning its result with what it wrote to `out` and `log`.
+    fn run_line(line: &[&str]) -> (crate::Result<()>, Vec<u8>, String) {
+        run_with_input(line, b"")
+    }
+
+    /// Runs a command line with `input` standing in for stdin.
+    fn run_with_input(line: &[&str], input: &[u8]) -> (crate::Result<()>, Vec<u8>, String) {
+        let matches = Arguments::command()
+            .try_get_matches_from(std::iter::once("pngme").chain(line.iter().copied()))
+            .unwrap();
+        let arguments = Arguments::from_matches(&matches, &Config::default()).unwrap();
+        let (mut out, mut log) = (vec![], vec![]);
+        let result = run(&arguments, &mut &input[..], &mut out, &mut log);
+        (result, out, String::from_utf8(log).unwrap())
+    }
+
+    /// An RGB source with chunks on both sides of IDAT, and an indexed destination.
+    fn transplant_files(name: &str) -> (PathBuf, String, String) {
+        let directory = temp_directory(name);
+        let source = write_file(
+            &directory.join("source.png"),
+            vec![
+                chunk("IHDR", RGB_HEADER),
+                chunk("gAMA", [0, 0, 177, 143]),
+                chunk("cHRM", [0; 32]),
+                chunk("IDAT", b""),
+                chunk("tEXt", b"Comment\0copied"),
+                chunk("IEND", b""),
+            ],
+        );
+        let destination = write_file(
+            &directory.join("destination.png"),
+            vec![
+                chunk("IHDR", INDEXED_HEADER),
+                chunk("PLTE", [0, 0, 0]),
+                chunk("IDAT", b""),
+                chunk("IEND", b""),
+            ],
+        );
+        (directory, source, destination)
+    }
+
+    #[test]
+    fn test_transplant_keeps_chunks_before_palette() {
+        let (directory, source, destination) = transplant_files("transplant-order");
+        let output = directory.join("out.png");
+        let output_arg = output.display().to_string();
+        let (result, _, log) = run_line(&[
+            "transplant",
+            &source,
+            &destination,
+            "-o",
+            &output_arg,
+            "--force",
+        ]);
+        result.unwrap();
+        assert_eq!(
+            types(&output),
+            ["IHDR", "gAMA", "cHRM", "PLTE", "IDAT", "tEXt", "IEND"]
+        );
+        assert!(log.contains("Copied gAMA (4 bytes)"));
+    }
+
+    #[test]
+    fn test_transplant_only_and_unsafe_chunks() {
+        let (directory, source, destination) = transplant_files("transplant-filter");
+        let output = directory.join("out.png");
+        let output_arg = output.display().to_string();
+
+        let line = ["transplant", &source, &destination, "-o", &output_arg];
+        let (result, _, _) = run_line(&[&line[..], &["--only", "tEXt", "--force"]].concat());
+        result.unwrap();
+        assert_eq!(types(&output), ["IHDR", "PLTE", "IDAT", "tEXt", "IEND"]);
+
+        let (result, _, log) = run_line(&line);
+        result.unwrap();
+        assert_eq!(types(&output), ["IHDR", "PLTE", "IDAT", "tEXt", "IEND"]);
+        assert!(log.contains("Skipped gAMA: unsafe to copy and critical chunks differ"));
+        assert!(log.contains("Skipped cHRM"));
+    }
+
+    fn stdin_png() -> Vec<u8> {
+        Png::from_chunks(vec![
+            chunk("IHDR", RGB_HEADER),
+            chunk("IDAT", b""),
+            chunk("ruSt", b"hidden\n"),
+            chunk("IEND", b""),
+        ])
+        .as_bytes()
+    }
+
+    fn chunk_data(bytes: &[u8], chunk_type: &str) -> Option<Vec<u8>> {
+        let png = Png::try_from(bytes).unwrap();
+        png.chunk_by_type(chunk_type)
+            .map(|chunk| chunk.data().to_vec())
+    }
+
+    #[test]
+    fn test_decode_from_stdin_writes_raw_bytes() {
+        let (result, out, log) = run_with_input(&["decode", "-", "ruSt"], &stdin_png());
+        result.unwrap();
+        assert_eq!(out, b"hidden\n");
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_encode_stdin_to_stdout() {
+        let (result, out, log) =
+            run_with_input(&["encode", "-", "teSt", "message", "-o", "-"], &stdin_png());
+        result.unwrap();
+        assert_eq!(chunk_data(&out, "teSt").unwrap(), b"message");
+        assert_eq!(chunk_data(&out, "ruSt").unwrap(), b"hidden\n");
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_encode_reads_omitted_message_from_stdin() {
+        let directory = temp_directory("encode-stdin");
+        let path = directory.join("image.png");
+        let file = write_file(&path, vec![chunk("IHDR", RGB_HEADER), chunk("IEND", b"")]);
+
+        let (result, out, _) = run_with_input(&["encode", &file, "teSt"], b"from\0stdin");
+        result.unwrap();
+        assert!(out.is_empty());
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(chunk_data(&bytes, "teSt").unwrap(), b"from\0stdin");
+    }
+
+    #[test]
+    fn test_encode_stdin_png_needs_message() {
+        let (result, out, _) = run_with_input(&["encode", "-", "teSt"], &stdin_png());
+        assert!(matches!(result, Err(Error::Usage(_))));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_strip_stdin_keeps_log_off_stdout() {
+        let (result, out, log) = run_with_input(&["strip", "-", "-o", "-"], &stdin_png());
+        result.unwrap();
+        assert_eq!(chunk_data(&out, "ruSt"), None);
+        assert!(chunk_data(&out, "IHDR").is_some());
+        assert!(log.contains("ruSt"));
+    }
+
+    /// A file with a private chunk and public ancillary chunks on both sides of IDAT.
+    fn strip_file(name: &str) -> (PathBuf, String) {
+        let path = temp_directory(name).join("image.png");
+        let file = write_file(
+            &path,
+            vec![
+                chunk("IHDR", RGB_HEADER),
+                chunk("gAMA", [0, 0, 177, 143]),
+                chunk("ruSt", b"secret"),
+                chunk("IDAT", b""),
+                chunk("tEXt", b"Comment\0hello"),
+                chunk("IEND", b""),
+            ],
+        );
+        (path, file)
+    }
+
+    #[test]
+    fn test_strip_removes_all_ancillary_chunks() {
+        let (path, file) = strip_file("strip-default");
+        let (result, out, log) = run_line(&["strip", &file]);
+        result.unwrap();
+        assert!(out.is_empty());
+        assert_eq!(types(&path), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(
+            log,
+            "Removed gAMA (public, 4 bytes): image gamma\n\
+             Removed ruSt (private, 6 bytes): unregistered\n\
+             Removed tEXt (public, 13 bytes): textual data\n\
+             Removed 3 chunks, 59 bytes\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_keep() {
+        let (path, file) = strip_file("strip-keep");
+        let (result, _, log) = run_line(&["strip", &file, "--keep", "gAMA", "--keep", "tEXt"]);
+        result.unwrap();
+        assert_eq!(types(&path), ["IHDR", "gAMA", "IDAT", "tEXt", "IEND"]);
+        assert!(log.ends_with("Removed 1 chunks, 18 bytes\n"));
+    }
+
+    #[test]
+    fn test_strip_only() {
+        let (path, file) = strip_file("strip-only");
+        let (result, _, log) = run_line(&["strip", &file, "--only", "tEXt", "--only", "IDAT"]);
+        result.unwrap();
+        assert_eq!(types(&path), ["IHDR", "gAMA", "ruSt", "IDAT", "IEND"]);
+        assert!(log.ends_with("Removed 1 chunks, 25 bytes\n"));
+    }
+
+    #[test]
+    fn test_strip_private_chunk_dry_run() {
+        let (path, file) = strip_file("strip-private");
+        let (result, out, log) = run_line(&["strip", &file, "--only", "ruSt", "--dry-run"]);
+        result.unwrap();
+        assert_eq!(types(&path).len(), 6);
+        assert!(String::from_utf8(out).unwrap().contains("ruSt"));
+        assert_eq!(
+            log,
+            "Would remove ruSt (private, 6 bytes): unregistered\n\
+             Would remove 1 chunks, 18 bytes\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_file_is_named() {
+        let path = temp_directory("missing").join("missing.png");
+        let file = path.display().to_string();
+        let (result, _, _) = run_line(&["print", &file]);
+        let error = result.unwrap_err();
+        assert_eq!(error.exit_code(), 3);
+        assert!(error.report().starts_with(&format!("{}: ", file)));
+    }
+}
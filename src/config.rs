@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+use serde::Deserialize;
+
+use crate::args::{Action, Placement};
+use crate::chunk_type::ChunkType;
+use crate::physical::PixelDimensions;
+
+/// The config file looked for in the current directory, before the user's config directory.
+pub const LOCAL_CONFIG_FILE: &str = ".pngme.toml";
+
+/// Defaults read from a TOML config file, for settings not given on the command line.
+///
+/// The top level holds the defaults for every run. Each `[profiles.<name>]` table takes the
+/// same keys, which replace the top-level ones when the profile is selected with `--profile`,
+/// and may list `steps`, the actions `pngme run` performs one after the other.
+#[derive(Clone, Default, PartialEq, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    chunk_type: Option<String>,
+    placement: Option<Placement>,
+    backup: Option<String>,
+    strip_trailer: Option<bool>,
+    hex: Option<bool>,
+    keep: Option<Vec<String>>,
+    jobs: Option<usize>,
+    dpi: Option<f64>,
+    touch: Option<bool>,
+    #[serde(default)]
+    steps: Vec<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Config>,
+}
+
+impl Config {
+    /// Reads the first config file found, or returns an empty config when there is none.
+    pub fn load() -> Result<Config, ConfigError> {
+        match Self::path() {
+            Some(path) => Self::from_file(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// `.pngme.toml` in the current directory, else `pngme/config.toml` in
+    /// `$XDG_CONFIG_HOME` or `~/.config`, whichever exists first.
+    pub fn path() -> Option<PathBuf> {
+        let local = PathBuf::from(LOCAL_CONFIG_FILE);
+        let user = env::var_os("XDG_CONFIG_HOME")
+            .filter(|directory| !directory.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|directory| directory.join("pngme").join("config.toml"));
+
+        std::iter::once(local)
+            .chain(user)
+            .find(|path| path.is_file())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path)
+            .map_err(|io_error| ConfigError::Io(path.to_path_buf(), io_error))?;
+        Self::parse(&text).map_err(|config_error| match config_error {
+            ConfigError::Parse(_, message) => ConfigError::Parse(path.to_path_buf(), message),
+            other => other,
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(text).map_err(|toml_error| {
+            ConfigError::Parse(PathBuf::new(), toml_error.message().into())
+        })?;
+
+        config.check_values()?;
+        if !config.steps.is_empty() {
+            return Err(ConfigError::Parse(
+                PathBuf::new(),
+                "steps can only be given in a profile".into(),
+            ));
+        }
+        for (name, profile) in &config.profiles {
+            if !profile.profiles.is_empty() {
+                return Err(ConfigError::Parse(
+                    PathBuf::new(),
+                    format!("profile {} cannot define profiles", name),
+                ));
+            }
+            profile.check_values()?;
+            profile.steps()?;
+        }
+        Ok(config)
+    }
+
+    /// Rejects a chunk-type or dpi that could not be used, rather than failing on first use.
+    fn check_values(&self) -> Result<(), ConfigError> {
+        let invalid = |key: &str, value: &dyn Display, reason: crate::Error| {
+            ConfigError::Parse(
+                PathBuf::new(),
+                format!("invalid {} {}: {}", key, value, reason.report()),
+            )
+        };
+        if let Some(chunk_type) = self.chunk_type.as_deref() {
+            ChunkType::from_str(chunk_type)
+                .map_err(|error| invalid("chunk-type", &chunk_type, error.into()))?;
+        }
+        if let Some(dpi) = self.dpi {
+            PixelDimensions::from_dpi(dpi).map_err(|error| invalid("dpi", &dpi, error.into()))?;
+        }
+        Ok(())
+    }
+
+    /// These settings with those of the named profile taking precedence.
+    pub fn with_profile(&self, name: &str) -> Result<Config, ConfigError> {
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownProfile(name.to_string()))?;
+        Ok(Config {
+            chunk_type: profile.chunk_type.clone().or(self.chunk_type.clone()),
+            placement: profile.placement.or(self.placement),
+            backup: profile.backup.clone().or(self.backup.clone()),
+            strip_trailer: profile.strip_trailer.or(self.strip_trailer),
+            hex: profile.hex.or(self.hex),
+            keep: profile.keep.clone().or(self.keep.clone()),
+            jobs: profile.jobs.or(self.jobs),
+            dpi: profile.dpi.or(self.dpi),
+            touch: profile.touch.or(self.touch),
+            steps: profile.steps.clone(),
+            profiles: BTreeMap::new(),
+        })
+    }
+
+    /// The chunk type messages are encoded in and decoded from when none is given.
+    pub fn chunk_type(&self) -> Option<&str> {
+        self.chunk_type.as_deref()
+    }
+
+    /// Where encode puts a new chunk.
+    pub fn placement(&self) -> Option<Placement> {
+        self.placement
+    }
+
+    /// The suffix for backups of overwritten files.
+    pub fn backup(&self) -> Option<&str> {
+        self.backup.as_deref()
+    }
+
+    pub fn strip_trailer(&self) -> Option<bool> {
+        self.strip_trailer
+    }
+
+    /// Whether print and show dump chunk data in hex.
+    pub fn hex(&self) -> Option<bool> {
+        self.hex
+    }
+
+    /// The ancillary chunk types strip keeps.
+    pub fn keep(&self) -> Option<&[String]> {
+        self.keep.as_deref()
+    }
+
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+
+    pub fn dpi(&self) -> Option<f64> {
+        self.dpi
+    }
+
+    pub fn touch(&self) -> Option<bool> {
+        self.touch
+    }
+
+    /// The actions of the selected profile, in the order `pngme run` performs them.
+    pub fn steps(&self) -> Result<Vec<Action>, ConfigError> {
+        self.steps
+            .iter()
+            .map(|step| match Action::from_str(step) {
+                Ok(
+                    action @ (Action::Encode
+                    | Action::Remove
+                    | Action::Insert
+                    | Action::Strip
+                    | Action::Profile
+                    | Action::Set
+                    | Action::Exif),
+                ) => Ok(action),
+                _ => Err(ConfigError::InvalidStep(step.clone())),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, String),
+    UnknownProfile(String),
+    InvalidStep(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, io_error) => {
+                write!(f, "Error: cannot read {}: {}", path.display(), io_error)
+            }
+            Self::Parse(path, message) => {
+                write!(f, "Error: invalid config {}: {}", path.display(), message)
+            }
+            Self::UnknownProfile(name) => {
+                write!(f, "Error: no profile named {} in the config", name)
+            }
+            Self::InvalidStep(step) => write!(
+                f,
+                "Error: {} cannot be a profile step, steps must be actions that rewrite the PNG",
+                step
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+chunk-type = "ruSt"
+placement = "before-data"
+keep = ["gAMA"]
+
+[profiles.publish]
+steps = ["strip", "set"]
+keep = ["sRGB", "iCCP"]
+touch = true
+"#;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config.chunk_type(), Some("ruSt"));
+        assert_eq!(config.placement(), Some(Placement::BeforeData));
+        assert!(config.steps().unwrap().is_empty());
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_profile_overrides_defaults() {
+        let config = Config::parse(CONFIG).unwrap();
+        let publish = config.with_profile("publish").unwrap();
+        assert_eq!(publish.keep().unwrap(), ["sRGB", "iCCP"]);
+        assert_eq!(publish.chunk_type(), Some("ruSt"));
+        assert_eq!(publish.touch(), Some(true));
+        assert!(publish.steps().unwrap() == [Action::Strip, Action::Set]);
+        assert!(matches!(
+            config.with_profile("draft"),
+            Err(ConfigError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_invalid_config() {
+        assert!(matches!(
+            Config::parse("chunk_tipe = \"ruSt\""),
+            Err(ConfigError::Parse(_, _))
+        ));
+        assert!(Config::parse("steps = [\"strip\"]").is_err());
+        assert!(matches!(
+            Config::parse("chunk-type = \"ru5t\""),
+            Err(ConfigError::Parse(_, message)) if message.starts_with("invalid chunk-type")
+        ));
+        assert!(Config::parse("[profiles.publish]\nchunk-type = \"r\"").is_err());
+        assert!(Config::parse("dpi = -300.0").is_err());
+        assert!(matches!(
+            Config::parse("[profiles.publish]\nsteps = [\"strip\", \"seal\"]"),
+            Err(ConfigError::InvalidStep(step)) if step == "seal"
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{fmt, fmt::Display};
+
+use crate::chunk::{ChunkError, DATA_LENGTH_BYTES, META_DATA_BYTES};
+use crate::chunk_type::ChunkTypeError;
+use crate::hex::HexDump;
+use crate::png::{Png, PngError};
+
+/// Bytes of context shown on each side of the bad bytes.
+const CONTEXT_BYTES: usize = 16;
+/// At most this many bad bytes are marked, so a truncated file does not dump its remainder.
+const MAX_HIGHLIGHT: usize = 32;
+
+/// Explains why `Png::try_from` rejected a file: where it failed, the bytes around that
+/// point, what was expected there and what to do about it.
+#[derive(Debug)]
+pub struct Diagnostic {
+    file_name: String,
+    error: PngError,
+    summary: String,
+    offset: usize,
+    length: usize,
+    expected: String,
+    actual: String,
+    hint: &'static str,
+    excerpt: String,
+}
+
+impl Diagnostic {
+    /// Describes `error`, returned for `bytes` read from `file_name`. Errors that do not come
+    /// from reading the bytes, such as a missing chunk, have no diagnostic.
+    pub fn new(file_name: &str, bytes: &[u8], error: PngError) -> Result<Diagnostic, PngError> {
+        let (summary, offset, length, expected, actual, hint) = match &error {
+            PngError::NotEnoughData => (
+                "too short to hold the PNG signature".to_string(),
+                0,
+                bytes.len(),
+                format!("at least {} bytes", Png::STANDARD_HEADER.len()),
+                format!("{} bytes", bytes.len()),
+                "the file is empty or was cut off, copy or download it again",
+            ),
+            PngError::PngHeaderIncorrect => {
+                let header = &bytes[..Png::STANDARD_HEADER.len()];
+                let differs = |index: &usize| header[*index] != Png::STANDARD_HEADER[*index];
+                let first = (0..header.len()).find(differs).unwrap_or(0);
+                let last = (0..header.len()).rfind(differs).unwrap_or(first);
+                let hint = match &header[1..4] == b"PNG" {
+                    true => "the line-ending bytes of the signature were changed, the file was probably transferred in text mode, transfer it again in binary mode",
+                    false => "this is not a PNG file",
+                };
+                (
+                    "wrong signature".to_string(),
+                    first,
+                    last + 1 - first,
+                    format!("signature {}", hex_bytes(&Png::STANDARD_HEADER)),
+                    format!("signature {}", hex_bytes(header)),
+                    hint,
+                )
+            }
+            PngError::PngChunkError(at, chunk_error) => {
+                let at = *at;
+                let left = bytes.len() - at;
+                match chunk_error {
+                    ChunkError::DataSampleSmall(_) => (
+                        format!("the file ends in the middle of the chunk at byte {}", at),
+                        at,
+                        left,
+                        format!("a chunk of at least {} bytes", META_DATA_BYTES),
+                        format!("{} bytes left", left),
+                        "the file was cut off, copy or download it again",
+                    ),
+                    ChunkError::DataTruncated(declared, available) => (
+                        format!("the {} chunk at byte {} is truncated", type_at(bytes, at), at),
+                        at,
+                        DATA_LENGTH_BYTES,
+                        format!("{} bytes of data and a 4-byte CRC", declared),
+                        format!("{} bytes left after the chunk type", available),
+                        "the file was cut off, or this length field is damaged, copy or download it again",
+                    ),
+                    ChunkError::ParsingChunkType(ChunkTypeError::InvalidByte(index, byte)) => (
+                        format!("invalid chunk type at byte {}", at + DATA_LENGTH_BYTES),
+                        at + DATA_LENGTH_BYTES + index,
+                        1,
+                        "a letter, A-Z or a-z".to_string(),
+                        format!("0x{:02x}", byte),
+                        match at == Png::STANDARD_HEADER.len() {
+                            true => "the first chunk should be IHDR, the file is damaged right after its signature",
+                            false => "the length of the previous chunk may be wrong, putting this chunk header inside chunk data",
+                        },
+                    ),
+                    ChunkError::CrcNotMatching(parsed, calculated) => {
+                        let length = u32::from_be_bytes(
+                            bytes[at..at + DATA_LENGTH_BYTES].try_into().unwrap(),
+                        ) as usize;
+                        (
+                            format!("CRC mismatch in the {} chunk at byte {}", type_at(bytes, at), at),
+                            at + META_DATA_BYTES - 4 + length,
+                            4,
+                            format!("CRC 0x{:08x}, computed from the chunk type and data", calculated),
+                            format!("CRC 0x{:08x}", parsed),
+                            "the chunk data or its CRC was changed after the file was written, get the file again from its source",
+                        )
+                    }
+                    _ => (
+                        format!("unreadable chunk at byte {}", at),
+                        at,
+                        left.min(META_DATA_BYTES),
+                        "a chunk length, type and CRC".to_string(),
+                        format!("{} bytes left", left),
+                        "the file is damaged, get it again from its source",
+                    ),
+                }
+            }
+            _ => return Err(error),
+        };
+
+        let length = length.clamp(1, MAX_HIGHLIGHT);
+        let start = offset.saturating_sub(CONTEXT_BYTES) / 16 * 16;
+        let end = (offset + length + CONTEXT_BYTES).min(bytes.len());
+        let excerpt = HexDump::new(bytes)
+            .range(start, Some(end.saturating_sub(start)))
+            .highlight(offset, length)
+            .to_string();
+
+        Ok(Diagnostic {
+            file_name: file_name.to_string(),
+            error,
+            summary,
+            offset,
+            length,
+            expected,
+            actual,
+            hint,
+            excerpt,
+        })
+    }
+
+    /// The error the diagnostic describes.
+    pub fn error(&self) -> &PngError {
+        &self.error
+    }
+
+    /// Where the bad bytes start in the file.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Error: {} is not a valid PNG: {}",
+            self.file_name, self.summary
+        )?;
+        writeln!(
+            f,
+            "  --> {} at byte {} (0x{:x})",
+            self.file_name, self.offset, self.offset
+        )?;
+        write!(f, "{}", self.excerpt)?;
+        writeln!(f, "  expected: {}", self.expected)?;
+        writeln!(f, "     found: {}", self.actual)?;
+        write!(f, "      hint: {}", self.hint)
+    }
+}
+
+/// The whole diagnostic is the message, so the error it describes is not repeated as a source.
+impl std::error::Error for Diagnostic {}
+
+fn type_at(bytes: &[u8], at: usize) -> String {
+    let start = at + DATA_LENGTH_BYTES;
+    bytes
+        .get(start..start + 4)
+        .map(|chunk_type| String::from_utf8_lossy(chunk_type).into_owned())
+        .unwrap_or_default()
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png_bytes() -> Vec<u8> {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec());
+        Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(chunk.as_bytes())
+            .collect()
+    }
+
+    fn diagnose(bytes: &[u8]) -> Diagnostic {
+        let error = Png::try_from(bytes).map(|_| ()).unwrap_err();
+        Diagnostic::new("dice.png", bytes, error).unwrap()
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let mut bytes = png_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        let diagnostic = diagnose(&bytes);
+        assert_eq!((diagnostic.offset(), diagnostic.length()), (22, 4));
+        let text = diagnostic.to_string();
+        assert!(
+            text.starts_with("Error: dice.png is not a valid PNG: CRC mismatch in the ruSt chunk")
+        );
+        assert!(text.contains("^^ ^^ ^^ ^^"));
+        assert!(text.contains("expected: CRC 0x"));
+    }
+
+    #[test]
+    fn test_bad_chunk_type_byte() {
+        let mut bytes = png_bytes();
+        bytes[14] = b'1';
+
+        let diagnostic = diagnose(&bytes);
+        assert_eq!(diagnostic.offset(), 14);
+        assert!(diagnostic.to_string().contains("found: 0x31"));
+    }
+
+    #[test]
+    fn test_text_mode_signature() {
+        let mut bytes = png_bytes();
+        bytes[4] = b'\n';
+
+        let diagnostic = diagnose(&bytes);
+        assert_eq!((diagnostic.offset(), diagnostic.length()), (4, 1));
+        assert!(diagnostic.to_string().contains("text mode"));
+    }
+}
@@ -0,0 +1,307 @@
+use std::ops::Range;
+use std::{fmt, fmt::Display};
+
+use crate::chunk::Chunk;
+use crate::text::TextChunk;
+
+/// One step of the alignment between two chunk lists, holding indices into them.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ChunkChange {
+    Unchanged(usize, usize),
+    Added(usize),
+    Removed(usize),
+    Modified(usize, usize),
+    Moved(usize, usize),
+}
+
+/// A chunk-level comparison of two chunk lists.
+pub struct ChunkDiff<'a> {
+    before: &'a [Chunk],
+    after: &'a [Chunk],
+    changes: Vec<ChunkChange>,
+}
+
+impl<'a> ChunkDiff<'a> {
+    /// Aligns the two lists on their longest common subsequence of identical chunks.
+    ///
+    /// A removed chunk that reappears unchanged elsewhere is reported as moved. Within each
+    /// gap between identical chunks, a removed chunk and an added chunk of the same type are
+    /// reported together as a modification.
+    pub fn new(before: &'a [Chunk], after: &'a [Chunk]) -> ChunkDiff<'a> {
+        let mut changes = vec![];
+        let (mut next_a, mut next_b) = (0, 0);
+
+        for (a, b) in common_subsequence(before, after)
+            .into_iter()
+            .chain([(before.len(), after.len())])
+        {
+            pair_gap(before, after, next_a..a, next_b..b, &mut changes);
+            if a < before.len() && b < after.len() {
+                changes.push(ChunkChange::Unchanged(a, b));
+            }
+            (next_a, next_b) = (a + 1, b + 1);
+        }
+        pair_moves(before, after, &mut changes);
+
+        ChunkDiff {
+            before,
+            after,
+            changes,
+        }
+    }
+
+    pub fn changes(&self) -> &[ChunkChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|change| matches!(change, ChunkChange::Unchanged(..)))
+    }
+
+    pub fn changed_count(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| !matches!(change, ChunkChange::Unchanged(..)))
+            .count()
+    }
+}
+
+impl Display for ChunkDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "  no changes");
+        }
+
+        for change in self.changes.iter() {
+            match *change {
+                ChunkChange::Unchanged(..) => continue,
+                ChunkChange::Added(b) => writeln!(
+                    f,
+                    "  + #{} {} ({} bytes)",
+                    b,
+                    self.after[b].chunk_type().to_string(),
+                    self.after[b].length()
+                )?,
+                ChunkChange::Removed(a) => writeln!(
+                    f,
+                    "  - #{} {} ({} bytes)",
+                    a,
+                    self.before[a].chunk_type().to_string(),
+                    self.before[a].length()
+                )?,
+                ChunkChange::Modified(a, b) => {
+                    writeln!(
+                        f,
+                        "  ~ #{} -> #{} {} ({} -> {} bytes)",
+                        a,
+                        b,
+                        self.after[b].chunk_type().to_string(),
+                        self.before[a].length(),
+                        self.after[b].length()
+                    )?;
+                    let texts = (
+                        TextChunk::try_from(&self.before[a]),
+                        TextChunk::try_from(&self.after[b]),
+                    );
+                    if let (Ok(before), Ok(after)) = texts {
+                        writeln!(f, "      - {}", before)?;
+                        writeln!(f, "      + {}", after)?;
+                    }
+                }
+                ChunkChange::Moved(a, b) => writeln!(
+                    f,
+                    "  > #{} -> #{} {} ({} bytes)",
+                    a,
+                    b,
+                    self.after[b].chunk_type().to_string(),
+                    self.after[b].length()
+                )?,
+            }
+        }
+        write!(f, "  {} chunks changed", self.changed_count())
+    }
+}
+
+fn pair_gap(
+    before: &[Chunk],
+    after: &[Chunk],
+    removed: Range<usize>,
+    added: Range<usize>,
+    changes: &mut Vec<ChunkChange>,
+) {
+    let mut added: Vec<usize> = added.collect();
+    for a in removed {
+        let chunk_type = before[a].chunk_type();
+        match added
+            .iter()
+            .position(|&b| after[b].chunk_type() == chunk_type)
+        {
+            Some(position) => {
+                for b in added.drain(..position) {
+                    changes.push(ChunkChange::Added(b));
+                }
+                changes.push(ChunkChange::Modified(a, added.remove(0)));
+            }
+            None => changes.push(ChunkChange::Removed(a)),
+        }
+    }
+    for b in added {
+        changes.push(ChunkChange::Added(b));
+    }
+}
+
+/// Turns a removal and an addition of the same chunk into a move.
+fn pair_moves(before: &[Chunk], after: &[Chunk], changes: &mut Vec<ChunkChange>) {
+    let mut index = 0;
+    while index < changes.len() {
+        if let ChunkChange::Removed(a) = changes[index] {
+            let added = changes.iter().position(|change| match *change {
+                ChunkChange::Added(b) => same_chunk(&before[a], &after[b]),
+                _ => false,
+            });
+            if let Some(position) = added {
+                let ChunkChange::Added(b) = changes.remove(position) else {
+                    unreachable!();
+                };
+                let index = if position < index { index - 1 } else { index };
+                changes[index] = ChunkChange::Moved(a, b);
+            }
+        }
+        index += 1;
+    }
+}
+
+fn same_chunk(a: &Chunk, b: &Chunk) -> bool {
+    a.chunk_type() == b.chunk_type() && a.data() == b.data()
+}
+
+/// Index pairs of the longest common subsequence of identical chunks, in order.
+///
+/// Rewritten files usually differ in a few chunks, so the common prefix and suffix are matched
+/// directly and only the chunks between them go through the quadratic table.
+fn common_subsequence(before: &[Chunk], after: &[Chunk]) -> Vec<(usize, usize)> {
+    let shorter = before.len().min(after.len());
+    let prefix = (0..shorter)
+        .take_while(|&i| same_chunk(&before[i], &after[i]))
+        .count();
+    let suffix = (0..shorter - prefix)
+        .take_while(|&i| same_chunk(&before[before.len() - 1 - i], &after[after.len() - 1 - i]))
+        .count();
+
+    let middle = middle_subsequence(
+        &before[prefix..before.len() - suffix],
+        &after[prefix..after.len() - suffix],
+    );
+    (0..prefix)
+        .map(|i| (i, i))
+        .chain(middle.into_iter().map(|(a, b)| (a + prefix, b + prefix)))
+        .chain(
+            (0..suffix)
+                .rev()
+                .map(|i| (before.len() - 1 - i, after.len() - 1 - i)),
+        )
+        .collect()
+}
+
+/// The longest common subsequence by dynamic programming over every pair of chunks.
+fn middle_subsequence(before: &[Chunk], after: &[Chunk]) -> Vec<(usize, usize)> {
+    let same = |a: usize, b: usize| same_chunk(&before[a], &after[b]);
+
+    let mut lengths = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for a in (0..before.len()).rev() {
+        for b in (0..after.len()).rev() {
+            lengths[a][b] = match same(a, b) {
+                true => lengths[a + 1][b + 1] + 1,
+                false => lengths[a + 1][b].max(lengths[a][b + 1]),
+            };
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut a, mut b) = (0, 0);
+    while a < before.len() && b < after.len() {
+        if same(a, b) {
+            pairs.push((a, b));
+            a += 1;
+            b += 1;
+        } else if lengths[a + 1][b] >= lengths[a][b + 1] {
+            a += 1;
+        } else {
+            b += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::chunk;
+
+    #[test]
+    fn test_identical_chunks() {
+        let chunks = vec![chunk("IHDR", "h"), chunk("IEND", "")];
+        let diff = ChunkDiff::new(&chunks, &chunks);
+        assert!(diff.is_empty());
+        assert_eq!(diff.changed_count(), 0);
+    }
+
+    #[test]
+    fn test_added_removed_and_modified() {
+        let before = vec![
+            chunk("IHDR", "h"),
+            chunk("tEXt", "old"),
+            chunk("ruSt", "gone"),
+            chunk("IEND", ""),
+        ];
+        let after = vec![
+            chunk("IHDR", "h"),
+            chunk("tEXt", "new"),
+            chunk("IEND", ""),
+            chunk("ruSt", "late"),
+        ];
+
+        let diff = ChunkDiff::new(&before, &after);
+
+        assert_eq!(
+            diff.changes(),
+            &[
+                ChunkChange::Unchanged(0, 0),
+                ChunkChange::Modified(1, 1),
+                ChunkChange::Removed(2),
+                ChunkChange::Unchanged(3, 2),
+                ChunkChange::Added(3),
+            ]
+        );
+        assert_eq!(diff.changed_count(), 3);
+    }
+
+    #[test]
+    fn test_moved_chunks() {
+        let before = vec![chunk("gAMA", "g"), chunk("sRGB", "s"), chunk("IDAT", "d")];
+        let after = vec![chunk("sRGB", "s"), chunk("gAMA", "g"), chunk("IDAT", "d")];
+
+        let diff = ChunkDiff::new(&before, &after);
+
+        assert!(diff.changes().contains(&ChunkChange::Moved(0, 1)));
+        assert_eq!(diff.changed_count(), 1);
+    }
+
+    #[test]
+    fn test_many_chunks_with_few_changes() {
+        let before: Vec<Chunk> = (0..20_000).map(|i| chunk("IDAT", i.to_string())).collect();
+        let mut after = before.clone();
+        after[10_000] = chunk("IDAT", "changed");
+        after.insert(10_002, chunk("tEXt", "added"));
+
+        let diff = ChunkDiff::new(&before, &after);
+
+        assert_eq!(diff.changed_count(), 2);
+        assert!(diff
+            .changes()
+            .contains(&ChunkChange::Modified(10_000, 10_000)));
+        assert!(diff.changes().contains(&ChunkChange::Added(10_002)));
+    }
+}
@@ -0,0 +1,339 @@
+use std::io::Write;
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec;
+use crate::hex::HexDump;
+use crate::png::Png;
+use crate::registry;
+use crate::text::TextChunk;
+
+const HELP: &str = "\
+list                     list the chunks with their index
+show <n>                 describe chunk n, with its decoded value when known
+hexdump <n> [off] [len]  dump the data of chunk n, optionally a window of it
+add <TYPE> <data>        add a chunk holding data before IEND
+delete <n>               delete chunk n
+move <n> <to>            move chunk n to index to
+retype <n> <TYPE>        change the type of chunk n, keeping its data
+text <n> <text>          replace the text of a tEXt, zTXt or iTXt chunk
+undo                     revert the last change
+write [path]             validate and save, to FILE_PATH by default
+quit                     leave, quit! discards unsaved changes
+help                     show this list";
+
+/// What the shell should do after a command has run.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Step {
+    Continue,
+    Write(Option<String>),
+    Quit,
+}
+
+/// An in-memory PNG edited one command at a time, keeping every previous version for undo.
+///
+/// `version` counts every change and undo, so a version equal to `saved` means nothing has
+/// happened since the last write, even when the history has returned to the same length.
+pub struct Editor {
+    png: Png,
+    history: Vec<Png>,
+    version: usize,
+    saved: usize,
+}
+
+impl Editor {
+    pub fn new(png: Png) -> Editor {
+        Editor {
+            png,
+            history: vec![],
+            version: 0,
+            saved: 0,
+        }
+    }
+
+    pub fn png(&self) -> &Png {
+        &self.png
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.version != self.saved
+    }
+
+    /// Records that the current version was written, so quitting no longer warns.
+    pub fn mark_saved(&mut self) {
+        self.saved = self.version;
+    }
+
+    /// Runs one command line, writing anything it prints to `out`.
+    pub fn execute(&mut self, line: &str, out: &mut dyn Write) -> crate::Result<Step> {
+        let (command, rest) = split_word(line);
+
+        match command {
+            "" => {}
+            "help" => writeln!(out, "{}", HELP)?,
+            "list" => {
+                for (index, chunk) in self.png.chunks().iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{:>3} {} ({} bytes)",
+                        index,
+                        chunk.chunk_type().to_string(),
+                        chunk.length()
+                    )?;
+                }
+            }
+            "show" => {
+                let chunk = self.chunk(rest)?;
+                writeln!(out, "{}", chunk.summary())?;
+                if let Some(info) = registry::lookup(&chunk.chunk_type().to_string()) {
+                    writeln!(out, "{}", info)?;
+                }
+                if let Some(value) = codec::render(chunk) {
+                    writeln!(out, "{}", value?)?;
+                }
+            }
+            "hexdump" => {
+                let (index, rest) = split_word(rest);
+                let (offset, length) = split_word(rest);
+                let offset = match offset {
+                    "" => 0,
+                    _ => parse_index(offset)?,
+                };
+                let length = (!length.is_empty())
+                    .then(|| parse_index(length))
+                    .transpose()?;
+                let dump = HexDump::new(self.chunk(index)?.data()).range(offset, length);
+                write!(out, "{}", dump)?;
+            }
+            "add" => {
+                let (chunk_type, data) = split_word(rest);
+                let chunk_type = parse_chunk_type(chunk_type)?;
+                self.change(|png| {
+                    png.append_chunk(Chunk::new(chunk_type, data.as_bytes().to_vec()));
+                    Ok(())
+                })?;
+            }
+            "delete" => {
+                let index = self.index(rest)?;
+                self.change(|png| {
+                    png.remove_chunk_at(index);
+                    Ok(())
+                })?;
+            }
+            "move" => {
+                let (from, to) = split_word(rest);
+                let from = self.index(from)?;
+                let to = parse_index(to)?.min(self.png.chunks().len() - 1);
+                self.change(|png| {
+                    let chunk = png
+                        .remove_chunk_at(from)
+                        .ok_or(EditError::NoSuchChunk(from))?;
+                    png.insert_chunk(to, chunk);
+                    Ok(())
+                })?;
+            }
+            "retype" => {
+                let (index, chunk_type) = split_word(rest);
+                let index = self.index(index)?;
+                let chunk_type = parse_chunk_type(chunk_type)?;
+                self.change(|png| {
+                    let data = png.chunks()[index].data().to_vec();
+                    png.replace_chunk(index, Chunk::new(chunk_type, data));
+                    Ok(())
+                })?;
+            }
+            "text" => {
+                let (index, text) = split_word(rest);
+                let index = self.index(index)?;
+                let chunk = &self.png.chunks()[index];
+                let edited = TextChunk::try_from(chunk)?
+                    .with_text(text)
+                    .to_chunk(chunk.chunk_type())?;
+                self.change(|png| {
+                    png.replace_chunk(index, edited);
+                    Ok(())
+                })?;
+            }
+            "undo" => {
+                self.png = self.history.pop().ok_or(EditError::NothingToUndo)?;
+                self.version += 1;
+            }
+            "write" => {
+                self.png.validate_structure()?;
+                let path = (!rest.is_empty()).then(|| rest.to_string());
+                return Ok(Step::Write(path));
+            }
+            "quit" if self.has_unsaved_changes() => return Err(EditError::UnsavedChanges.into()),
+            "quit" | "quit!" => return Ok(Step::Quit),
+            _ => return Err(EditError::UnknownCommand(command.to_string()).into()),
+        }
+
+        Ok(Step::Continue)
+    }
+
+    /// Applies `edit` to a copy of the image, keeping the current version for undo.
+    fn change<F>(&mut self, edit: F) -> crate::Result<()>
+    where
+        F: FnOnce(&mut Png) -> crate::Result<()>,
+    {
+        let mut png = self.png.clone();
+        edit(&mut png)?;
+        self.history.push(std::mem::replace(&mut self.png, png));
+        self.version += 1;
+        Ok(())
+    }
+
+    fn index(&self, argument: &str) -> Result<usize, EditError> {
+        let index = parse_index(argument)?;
+        match index < self.png.chunks().len() {
+            true => Ok(index),
+            false => Err(EditError::NoSuchChunk(index)),
+        }
+    }
+
+    fn chunk(&self, argument: &str) -> Result<&Chunk, EditError> {
+        Ok(&self.png.chunks()[self.index(argument)?])
+    }
+}
+
+/// Splits off the first word of `line`, returning it and the rest with spaces trimmed.
+fn split_word(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim_start()),
+        None => (line, ""),
+    }
+}
+
+fn parse_index(argument: &str) -> Result<usize, EditError> {
+    match argument {
+        "" => Err(EditError::MissingArgument),
+        _ => argument
+            .parse()
+            .map_err(|_| EditError::InvalidIndex(argument.to_string())),
+    }
+}
+
+fn parse_chunk_type(argument: &str) -> crate::Result<ChunkType> {
+    match argument {
+        "" => Err(EditError::MissingArgument.into()),
+        _ => Ok(ChunkType::from_str(argument)?),
+    }
+}
+
+#[derive(Debug)]
+pub enum EditError {
+    UnknownCommand(String),
+    MissingArgument,
+    InvalidIndex(String),
+    NoSuchChunk(usize),
+    NothingToUndo,
+    UnsavedChanges,
+    DiscardedChanges,
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownCommand(command) => {
+                write!(f, "Unknown command {}, type help to list commands", command)
+            }
+            Self::MissingArgument => write!(f, "Missing argument, type help to list commands"),
+            Self::InvalidIndex(index) => write!(f, "{} is not a chunk index", index),
+            Self::NoSuchChunk(index) => write!(f, "There is no chunk {}", index),
+            Self::NothingToUndo => write!(f, "Nothing to undo"),
+            Self::UnsavedChanges => {
+                write!(f, "There are unsaved changes, write them or use quit!")
+            }
+            Self::DiscardedChanges => {
+                write!(f, "Input ended with unsaved changes, they were not written")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::chunk;
+
+    fn editor() -> Editor {
+        Editor::new(Png::from_chunks(vec![
+            chunk("IHDR", [0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            chunk("tEXt", b"Comment\0old"),
+            chunk("IDAT", b""),
+            chunk("IEND", b""),
+        ]))
+    }
+
+    fn types(editor: &Editor) -> Vec<String> {
+        editor
+            .png()
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    fn run(editor: &mut Editor, line: &str) -> crate::Result<Step> {
+        editor.execute(line, &mut vec![])
+    }
+
+    #[test]
+    fn test_add_move_and_undo() {
+        let mut editor = editor();
+        run(&mut editor, "add ruSt hello world").unwrap();
+        assert_eq!(types(&editor), ["IHDR", "tEXt", "IDAT", "ruSt", "IEND"]);
+        assert_eq!(editor.png().chunks()[3].data(), b"hello world");
+
+        run(&mut editor, "move 3 1").unwrap();
+        assert_eq!(types(&editor), ["IHDR", "ruSt", "tEXt", "IDAT", "IEND"]);
+
+        run(&mut editor, "undo").unwrap();
+        run(&mut editor, "undo").unwrap();
+        assert_eq!(types(&editor), ["IHDR", "tEXt", "IDAT", "IEND"]);
+        assert!(run(&mut editor, "undo").is_err());
+    }
+
+    #[test]
+    fn test_edit_text() {
+        let mut editor = editor();
+        run(&mut editor, "text 1 new text").unwrap();
+        let text = TextChunk::try_from(&editor.png().chunks()[1]).unwrap();
+        assert_eq!(text.keyword(), "Comment");
+        assert_eq!(text.text(), "new text");
+    }
+
+    #[test]
+    fn test_write_validates_and_quit_warns() {
+        let mut editor = editor();
+        run(&mut editor, "delete 3").unwrap();
+        assert!(run(&mut editor, "write").is_err());
+        assert!(run(&mut editor, "quit").is_err());
+
+        run(&mut editor, "undo").unwrap();
+        assert_eq!(
+            run(&mut editor, "write out.png").unwrap(),
+            Step::Write(Some("out.png".to_string()))
+        );
+        editor.mark_saved();
+        assert_eq!(run(&mut editor, "quit").unwrap(), Step::Quit);
+    }
+
+    #[test]
+    fn test_quit_warns_after_undo_past_write() {
+        let mut editor = editor();
+        run(&mut editor, "add ruSt first").unwrap();
+        run(&mut editor, "write").unwrap();
+        editor.mark_saved();
+
+        run(&mut editor, "undo").unwrap();
+        run(&mut editor, "add ruSt second").unwrap();
+        assert!(editor.has_unsaved_changes());
+        assert!(run(&mut editor, "quit").is_err());
+    }
+}
@@ -0,0 +1,393 @@
+use std::error::Error as StdError;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::{fmt, fmt::Display};
+
+use crate::apng::ApngError;
+use crate::args::ArgsErr;
+use crate::batch::BatchError;
+use crate::chunk::ChunkError;
+use crate::chunk_type::ChunkTypeError;
+use crate::codec::CodecError;
+use crate::color::ColorError;
+use crate::config::ConfigError;
+use crate::diagnostic::Diagnostic;
+use crate::editor::EditError;
+use crate::exif::ExifError;
+use crate::ihdr::IhdrError;
+use crate::image_data::ImageDataError;
+use crate::palette::PaletteError;
+use crate::physical::PhysicalError;
+use crate::png::PngError;
+use crate::text::TextError;
+
+type Source = Box<dyn StdError + Send + Sync>;
+
+/// Every error the crate returns, grouped by what went wrong so that callers and scripts can
+/// react to the kind of failure without matching on messages.
+///
+/// Each variant wraps the error that caused it, which stays reachable through `source`.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line or config asked for something that cannot be done.
+    Usage(Source),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The input is not in the expected format, such as a chunk whose fields are invalid.
+    Parse(Source),
+    /// The file is damaged: a CRC mismatch, truncated data or a broken chunk layout.
+    Integrity(Source),
+    /// Encryption or decryption failed.
+    Crypto(Source),
+    /// What was asked for, such as a chunk holding a message, is not in the file.
+    NotFound(Source),
+    /// `diff` found differences between the two images.
+    Differs,
+    /// Some files of a batch failed, the first failure being kept.
+    Batch {
+        failed: usize,
+        total: usize,
+        first: Box<Error>,
+    },
+}
+
+/// The kind of an [`Error`], with its stable code and process exit status.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    Differs,
+    Usage,
+    Io,
+    Parse,
+    Integrity,
+    Crypto,
+    NotFound,
+}
+
+impl ErrorKind {
+    /// A short name for the kind that will not change between releases.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Differs => "differs",
+            Self::Usage => "usage",
+            Self::Io => "io",
+            Self::Parse => "parse",
+            Self::Integrity => "integrity",
+            Self::Crypto => "crypto",
+            Self::NotFound => "not-found",
+        }
+    }
+
+    /// The process exit status: 1 when images differ, as with `cmp`, 2 for usage errors, as
+    /// clap already does, then 3 to 7 in the order of the variants.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Differs => 1,
+            Self::Usage => 2,
+            Self::Io => 3,
+            Self::Parse => 4,
+            Self::Integrity => 5,
+            Self::Crypto => 6,
+            Self::NotFound => 7,
+        }
+    }
+}
+
+impl ErrorKind {
+    fn of_chunk(error: &ChunkError) -> ErrorKind {
+        match error {
+            ChunkError::ParsingChunkType(_) => ErrorKind::Parse,
+            _ => ErrorKind::Integrity,
+        }
+    }
+
+    /// A bad signature is a parse error. Damage found after it, a CRC mismatch, truncation or
+    /// a broken layout, is an integrity error.
+    pub fn of_png(error: &PngError) -> ErrorKind {
+        match error {
+            PngError::PngChunkError(_, chunk_error) => Self::of_chunk(chunk_error),
+            PngError::FailedToRemoveChunk => ErrorKind::NotFound,
+            PngError::PngHeaderIncorrect => ErrorKind::Parse,
+            _ => ErrorKind::Integrity,
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl Error {
+    /// Wraps `source` in the variant for `kind`. `Differs` carries no source, so it is dropped.
+    pub fn new(kind: ErrorKind, source: Source) -> Error {
+        match kind {
+            ErrorKind::Differs => Error::Differs,
+            ErrorKind::Usage => Error::Usage(source),
+            ErrorKind::Io => Error::Io(io::Error::other(source)),
+            ErrorKind::Parse => Error::Parse(source),
+            ErrorKind::Integrity => Error::Integrity(source),
+            ErrorKind::Crypto => Error::Crypto(source),
+            ErrorKind::NotFound => Error::NotFound(source),
+        }
+    }
+
+    pub fn usage(message: impl Into<String>) -> Error {
+        Error::Usage(message.into().into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Error {
+        Error::NotFound(message.into().into())
+    }
+
+    /// An I/O failure on `path`, keeping the path in the message.
+    pub fn file(path: &Path, error: io::Error) -> Error {
+        let kind = error.kind();
+        Error::Io(io::Error::new(
+            kind,
+            FileError {
+                path: path.to_path_buf(),
+                error,
+            },
+        ))
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Usage(_) => ErrorKind::Usage,
+            Self::Io(_) => ErrorKind::Io,
+            Self::Parse(_) => ErrorKind::Parse,
+            Self::Integrity(_) => ErrorKind::Integrity,
+            Self::Crypto(_) => ErrorKind::Crypto,
+            Self::NotFound(_) => ErrorKind::NotFound,
+            Self::Differs => ErrorKind::Differs,
+            Self::Batch { first, .. } => first.kind(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind().code()
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+
+    /// The message of this error followed by those of its sources, on one line.
+    pub fn report(&self) -> String {
+        let mut messages = vec![self.to_string()];
+        let mut source = self.source();
+        while let Some(error) = source {
+            messages.push(error.to_string());
+            source = error.source();
+        }
+        messages
+            .iter()
+            .map(|message| message.strip_prefix("Error: ").unwrap_or(message))
+            .collect::<Vec<_>>()
+            .join(": ")
+    }
+
+    fn inner(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Usage(error)
+            | Self::Parse(error)
+            | Self::Integrity(error)
+            | Self::Crypto(error)
+            | Self::NotFound(error) => Some(error.as_ref()),
+            Self::Io(error) => Some(error),
+            Self::Differs | Self::Batch { .. } => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Differs => write!(f, "The images differ"),
+            Self::Batch { failed, total, .. } => write!(f, "{} of {} files failed", failed, total),
+            _ => write!(
+                f,
+                "{}",
+                self.inner().expect("every other variant wraps an error")
+            ),
+        }
+    }
+}
+
+impl StdError for Error {
+    /// The wrapped error is displayed in place of this one, so the chain continues with its
+    /// own source.
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Batch { first, .. } => Some(first.as_ref()),
+            _ => self.inner().and_then(|error| error.source()),
+        }
+    }
+}
+
+/// Reading or writing a named file failed.
+#[derive(Debug)]
+pub struct FileError {
+    path: PathBuf,
+    error: io::Error,
+}
+
+impl FileError {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {}: {}", self.path.display(), self.error)
+    }
+}
+
+impl StdError for FileError {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+macro_rules! error_kind {
+    ($($error:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::$variant(Box::new(error))
+                }
+            }
+        )*
+    };
+}
+
+error_kind! {
+    ArgsErr => Usage,
+    ChunkTypeError => Parse,
+    CodecError => Parse,
+    ColorError => Parse,
+    IhdrError => Parse,
+    PaletteError => Parse,
+    PhysicalError => Parse,
+    TextError => Parse,
+    ImageDataError => Integrity,
+}
+
+impl From<ChunkError> for Error {
+    fn from(error: ChunkError) -> Self {
+        Error::new(ErrorKind::of_chunk(&error), Box::new(error))
+    }
+}
+
+impl From<PngError> for Error {
+    fn from(error: PngError) -> Self {
+        Error::new(ErrorKind::of_png(&error), Box::new(error))
+    }
+}
+
+impl From<Diagnostic> for Error {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Error::new(ErrorKind::of_png(diagnostic.error()), Box::new(diagnostic))
+    }
+}
+
+impl From<ApngError> for Error {
+    fn from(error: ApngError) -> Self {
+        match error {
+            ApngError::NotAnimated | ApngError::NoSuchFrame(_, _) => {
+                Error::NotFound(Box::new(error))
+            }
+            ApngError::SequenceMismatch(_, _) | ApngError::FrameCountMismatch(_, _) => {
+                Error::Integrity(Box::new(error))
+            }
+            _ => Error::Parse(Box::new(error)),
+        }
+    }
+}
+
+impl From<ExifError> for Error {
+    fn from(error: ExifError) -> Self {
+        match error {
+            ExifError::MissingTag(_) => Error::NotFound(Box::new(error)),
+            _ => Error::Parse(Box::new(error)),
+        }
+    }
+}
+
+impl From<EditError> for Error {
+    fn from(error: EditError) -> Self {
+        match error {
+            EditError::NoSuchChunk(_) => Error::NotFound(Box::new(error)),
+            _ => Error::Usage(Box::new(error)),
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(error: ConfigError) -> Self {
+        match error {
+            ConfigError::Io(_, _) => Error::Io(io::Error::other(error)),
+            _ => Error::Usage(Box::new(error)),
+        }
+    }
+}
+
+impl From<BatchError> for Error {
+    fn from(error: BatchError) -> Self {
+        match error {
+            BatchError::NoMatches(_) => Error::NotFound(Box::new(error)),
+            BatchError::ReadDirectory(_, _) => Error::Io(io::Error::other(error)),
+            _ => Error::Usage(Box::new(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::Png;
+
+    #[test]
+    fn test_error_kinds() {
+        let corrupt: Error = Png::try_from([137u8, 80, 78, 71].as_ref())
+            .map(|_| ())
+            .unwrap_err()
+            .into();
+        assert_eq!(corrupt.kind(), ErrorKind::Integrity);
+        assert_eq!(corrupt.exit_code(), 5);
+
+        let missing: Error = PngError::FailedToRemoveChunk.into();
+        assert_eq!(missing.code(), "not-found");
+        assert_eq!(Error::usage("bad flag").exit_code(), 2);
+
+        let io_error = io::Error::from(io::ErrorKind::NotFound);
+        let file = Error::file(Path::new("missing.png"), io_error);
+        assert_eq!(file.exit_code(), 3);
+        assert_eq!(file.report(), "missing.png: entity not found");
+    }
+
+    #[test]
+    fn test_report_follows_sources() {
+        let error: Error = ChunkError::ParsingChunkType(ChunkTypeError::InvalidLength(3)).into();
+        assert_eq!(error.kind(), ErrorKind::Parse);
+        assert_eq!(
+            error.report(),
+            "Could not parse chunk type: a chunk type must have 4 characters, found 3"
+        );
+
+        let batch = Error::Batch {
+            failed: 1,
+            total: 2,
+            first: Box::new(Error::not_found("No chunk of type ruSt found")),
+        };
+        assert_eq!(batch.exit_code(), 7);
+        assert_eq!(
+            batch.report(),
+            "1 of 2 files failed: No chunk of type ruSt found"
+        );
+    }
+}
@@ -0,0 +1,652 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkCodec;
+
+const TIFF_HEADER_LENGTH: usize = 8;
+const ENTRY_LENGTH: usize = 12;
+const MAX_LISTED_VALUES: usize = 8;
+
+const ORIENTATION_TAG: u16 = 0x0112;
+const EXIF_POINTER_TAG: u16 = 0x8769;
+const GPS_POINTER_TAG: u16 = 0x8825;
+const INTEROP_POINTER_TAG: u16 = 0xa005;
+
+/// The image file directories an Exif block can hold.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Ifd {
+    Primary,
+    Thumbnail,
+    Exif,
+    Gps,
+    Interop,
+}
+
+impl Display for Ifd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Primary => write!(f, "IFD0"),
+            Self::Thumbnail => write!(f, "IFD1"),
+            Self::Exif => write!(f, "Exif"),
+            Self::Gps => write!(f, "GPS"),
+            Self::Interop => write!(f, "Interop"),
+        }
+    }
+}
+
+/// A tag value decoded according to its TIFF field type.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExifValue {
+    Ascii(String),
+    Unsigned(Vec<u32>),
+    Signed(Vec<i32>),
+    Rational(Vec<(u32, u32)>),
+    SignedRational(Vec<(i32, i32)>),
+    Float(Vec<f64>),
+    Undefined(Vec<u8>),
+}
+
+impl Display for ExifValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T: Display>(f: &mut fmt::Formatter<'_>, values: &[T]) -> fmt::Result {
+            let shown: Vec<String> = values
+                .iter()
+                .take(MAX_LISTED_VALUES)
+                .map(|value| value.to_string())
+                .collect();
+            write!(f, "{}", shown.join(", "))?;
+            if values.len() > MAX_LISTED_VALUES {
+                write!(f, ", ... ({} values)", values.len())?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Self::Ascii(text) => write!(f, "{:?}", text),
+            Self::Unsigned(values) => list(f, values),
+            Self::Signed(values) => list(f, values),
+            Self::Rational(values) => {
+                let values: Vec<String> =
+                    values.iter().map(|(n, d)| format!("{}/{}", n, d)).collect();
+                list(f, &values)
+            }
+            Self::SignedRational(values) => {
+                let values: Vec<String> =
+                    values.iter().map(|(n, d)| format!("{}/{}", n, d)).collect();
+                list(f, &values)
+            }
+            Self::Float(values) => list(f, values),
+            Self::Undefined(bytes) => {
+                let values: Vec<String> =
+                    bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                list(f, &values)
+            }
+        }
+    }
+}
+
+/// One tag of an IFD, with where its entry and value sit in the Exif block.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExifEntry {
+    ifd: Ifd,
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    entry_offset: usize,
+    value_offset: usize,
+    value_length: usize,
+    value: ExifValue,
+}
+
+impl ExifEntry {
+    pub fn ifd(&self) -> Ifd {
+        self.ifd
+    }
+
+    pub fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        tag_name(self.ifd, self.tag)
+    }
+
+    pub fn value(&self) -> &ExifValue {
+        &self.value
+    }
+
+    /// Whether the value is stored away from the entry rather than in its last four bytes.
+    fn is_out_of_line(&self) -> bool {
+        self.value_length > 4
+    }
+}
+
+impl Display for ExifEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} {}: {}", self.ifd, name, self.value),
+            None => write!(f, "{} 0x{:04x}: {}", self.ifd, self.tag, self.value),
+        }
+    }
+}
+
+/// The eXIf chunk: a TIFF header and its IFDs, in either byte order.
+///
+/// Edits are made in place so that the offsets of every tag left untouched stay valid.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Exif {
+    data: Vec<u8>,
+    big_endian: bool,
+}
+
+impl Exif {
+    pub const CHUNK_TYPE: &'static str = "eXIf";
+
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Every tag of IFD0, IFD1 and the Exif, GPS and interoperability IFDs, in file order.
+    pub fn entries(&self) -> Result<Vec<ExifEntry>, ExifError> {
+        let mut entries = vec![];
+        let mut visited = HashSet::new();
+        let mut pending = vec![(Ifd::Primary, self.u32_at(4)? as usize)];
+
+        while let Some((ifd, offset)) = pending.pop() {
+            if !visited.insert(offset) {
+                return Err(ExifError::Loop(offset));
+            }
+            let ifd_entries = self.read_ifd(ifd, offset)?;
+            for entry in &ifd_entries {
+                let pointed = match entry.tag {
+                    EXIF_POINTER_TAG => Some(Ifd::Exif),
+                    GPS_POINTER_TAG => Some(Ifd::Gps),
+                    INTEROP_POINTER_TAG => Some(Ifd::Interop),
+                    _ => None,
+                };
+                if let (Some(pointed), ExifValue::Unsigned(values)) = (pointed, &entry.value) {
+                    if let Some(&pointer) = values.first() {
+                        pending.push((pointed, pointer as usize));
+                    }
+                }
+            }
+            if ifd == Ifd::Primary {
+                let next = self.u32_at(offset + 2 + ifd_entries.len() * ENTRY_LENGTH)? as usize;
+                if next != 0 {
+                    pending.push((Ifd::Thumbnail, next));
+                }
+            }
+            entries.extend(ifd_entries);
+        }
+
+        entries.sort_by_key(|entry| entry.entry_offset);
+        Ok(entries)
+    }
+
+    /// The Orientation tag of IFD0, 1 to 8, when present.
+    pub fn orientation(&self) -> Result<Option<u16>, ExifError> {
+        Ok(self
+            .orientation_entry()?
+            .and_then(|entry| match entry.value {
+                ExifValue::Unsigned(values) => values.first().map(|&value| value as u16),
+                _ => Some(0),
+            }))
+    }
+
+    /// Rewrites the Orientation tag of IFD0. The tag must already be present, since adding
+    /// one would move every value stored after IFD0.
+    pub fn set_orientation(&mut self, orientation: u16) -> Result<(), ExifError> {
+        if !(1..=8).contains(&orientation) {
+            return Err(ExifError::InvalidOrientation(orientation));
+        }
+        let entry = self
+            .orientation_entry()?
+            .ok_or(ExifError::MissingTag("Orientation"))?;
+        if entry.field_type != 3 || entry.count != 1 {
+            return Err(ExifError::InvalidOrientation(orientation));
+        }
+        self.set_u16(entry.value_offset, orientation);
+        Ok(())
+    }
+
+    /// Removes the GPS IFD pointer from IFD0 and zeroes the GPS IFD and its values, returning
+    /// whether there was any location data.
+    pub fn remove_gps(&mut self) -> Result<bool, ExifError> {
+        let entries = self.entries()?;
+        let Some(pointer) = entries
+            .iter()
+            .find(|entry| entry.ifd == Ifd::Primary && entry.tag == GPS_POINTER_TAG)
+        else {
+            return Ok(false);
+        };
+
+        let gps: Vec<&ExifEntry> = entries.iter().filter(|e| e.ifd == Ifd::Gps).collect();
+        for entry in gps.iter().filter(|entry| entry.is_out_of_line()) {
+            self.zero(entry.value_offset, entry.value_length);
+        }
+        // A pointer with no value points at no GPS IFD, so only the entry itself is removed.
+        if let ExifValue::Unsigned(values) = &pointer.value {
+            if let Some(&gps_offset) = values.first() {
+                self.zero(gps_offset as usize, 2 + gps.len() * ENTRY_LENGTH + 4);
+            }
+        }
+
+        // Shifts the entries after the pointer, and the next IFD offset, up by one entry.
+        let ifd_offset = self.u32_at(4)? as usize;
+        let count = self.u16_at(ifd_offset)? as usize;
+        let ifd_end = ifd_offset + 2 + count * ENTRY_LENGTH + 4;
+        self.data.copy_within(
+            pointer.entry_offset + ENTRY_LENGTH..ifd_end,
+            pointer.entry_offset,
+        );
+        self.zero(ifd_end - ENTRY_LENGTH, ENTRY_LENGTH);
+        self.set_u16(ifd_offset, count as u16 - 1);
+        Ok(true)
+    }
+
+    fn orientation_entry(&self) -> Result<Option<ExifEntry>, ExifError> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.ifd == Ifd::Primary && entry.tag == ORIENTATION_TAG))
+    }
+
+    fn read_ifd(&self, ifd: Ifd, offset: usize) -> Result<Vec<ExifEntry>, ExifError> {
+        let count = self.u16_at(offset)? as usize;
+        (0..count)
+            .map(|index| self.read_entry(ifd, offset + 2 + index * ENTRY_LENGTH))
+            .collect()
+    }
+
+    fn read_entry(&self, ifd: Ifd, entry_offset: usize) -> Result<ExifEntry, ExifError> {
+        let tag = self.u16_at(entry_offset)?;
+        let field_type = self.u16_at(entry_offset + 2)?;
+        let count = self.u32_at(entry_offset + 4)?;
+        let size = match field_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => return Err(ExifError::UnknownFieldType(tag, field_type)),
+        };
+        let value_length = (count as usize)
+            .checked_mul(size)
+            .ok_or(ExifError::Truncated(entry_offset))?;
+        let value_offset = match value_length {
+            0..=4 => entry_offset + 8,
+            _ => self.u32_at(entry_offset + 8)? as usize,
+        };
+        let bytes = self.bytes(value_offset, value_length)?;
+
+        let value = match field_type {
+            2 => ExifValue::Ascii(
+                String::from_utf8_lossy(bytes)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            1 => ExifValue::Unsigned(bytes.iter().map(|&b| b as u32).collect()),
+            3 => ExifValue::Unsigned(self.words(bytes, 2).collect()),
+            4 => ExifValue::Unsigned(self.words(bytes, 4).collect()),
+            6 => ExifValue::Signed(bytes.iter().map(|&b| b as i8 as i32).collect()),
+            8 => ExifValue::Signed(self.words(bytes, 2).map(|w| w as i16 as i32).collect()),
+            9 => ExifValue::Signed(self.words(bytes, 4).map(|w| w as i32).collect()),
+            5 => {
+                let words: Vec<u32> = self.words(bytes, 4).collect();
+                ExifValue::Rational(words.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+            }
+            10 => {
+                let words: Vec<i32> = self.words(bytes, 4).map(|w| w as i32).collect();
+                ExifValue::SignedRational(words.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+            }
+            11 => ExifValue::Float(
+                self.words(bytes, 4)
+                    .map(|w| f32::from_bits(w) as f64)
+                    .collect(),
+            ),
+            12 => ExifValue::Float(
+                bytes
+                    .chunks(8)
+                    .map(|word| {
+                        let word: [u8; 8] = word.try_into().expect("chunks of 8 bytes");
+                        f64::from_bits(match self.big_endian {
+                            true => u64::from_be_bytes(word),
+                            false => u64::from_le_bytes(word),
+                        })
+                    })
+                    .collect(),
+            ),
+            _ => ExifValue::Undefined(bytes.to_vec()),
+        };
+
+        Ok(ExifEntry {
+            ifd,
+            tag,
+            field_type,
+            count,
+            entry_offset,
+            value_offset,
+            value_length,
+            value,
+        })
+    }
+
+    /// Splits `bytes` into words of `size` bytes, 2 or 4, in the block's byte order.
+    fn words<'a>(&self, bytes: &'a [u8], size: usize) -> impl Iterator<Item = u32> + 'a {
+        let big_endian = self.big_endian;
+        bytes.chunks(size).map(move |word| {
+            let mut padded = [0; 4];
+            match big_endian {
+                true => {
+                    padded[4 - size..].copy_from_slice(word);
+                    u32::from_be_bytes(padded)
+                }
+                false => {
+                    padded[..size].copy_from_slice(word);
+                    u32::from_le_bytes(padded)
+                }
+            }
+        })
+    }
+
+    fn bytes(&self, offset: usize, length: usize) -> Result<&[u8], ExifError> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(ExifError::Truncated(offset))
+    }
+
+    fn u16_at(&self, offset: usize) -> Result<u16, ExifError> {
+        Ok(self.words(self.bytes(offset, 2)?, 2).next().unwrap_or(0) as u16)
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32, ExifError> {
+        Ok(self.words(self.bytes(offset, 4)?, 4).next().unwrap_or(0))
+    }
+
+    fn set_u16(&mut self, offset: usize, value: u16) {
+        let bytes = match self.big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        self.data[offset..offset + 2].copy_from_slice(&bytes);
+    }
+
+    fn zero(&mut self, offset: usize, length: usize) {
+        let end = (offset + length).min(self.data.len());
+        if let Some(bytes) = self.data.get_mut(offset..end) {
+            bytes.fill(0);
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Exif {
+    type Error = ExifError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let big_endian = match data.get(..4) {
+            Some(b"II*\0") => false,
+            Some(b"MM\0*") => true,
+            _ => return Err(ExifError::InvalidHeader),
+        };
+        let exif = Exif {
+            data: data.to_vec(),
+            big_endian,
+        };
+        if (exif.u32_at(4)? as usize) < TIFF_HEADER_LENGTH {
+            return Err(ExifError::InvalidHeader);
+        }
+        Ok(exif)
+    }
+}
+
+impl Display for Exif {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = if self.big_endian { "big" } else { "little" };
+        match self.entries() {
+            Ok(entries) => write!(f, "{} tags, {} endian", entries.len(), order),
+            Err(exif_error) => write!(f, "{} endian, {}", order, exif_error),
+        }
+    }
+}
+
+/// The name of a tag, looked up in the GPS or interoperability namespace when it lives there.
+pub fn tag_name(ifd: Ifd, tag: u16) -> Option<&'static str> {
+    let name = match (ifd, tag) {
+        (Ifd::Gps, 0x00) => "GPSVersionID",
+        (Ifd::Gps, 0x01) => "GPSLatitudeRef",
+        (Ifd::Gps, 0x02) => "GPSLatitude",
+        (Ifd::Gps, 0x03) => "GPSLongitudeRef",
+        (Ifd::Gps, 0x04) => "GPSLongitude",
+        (Ifd::Gps, 0x05) => "GPSAltitudeRef",
+        (Ifd::Gps, 0x06) => "GPSAltitude",
+        (Ifd::Gps, 0x07) => "GPSTimeStamp",
+        (Ifd::Gps, 0x08) => "GPSSatellites",
+        (Ifd::Gps, 0x0c) => "GPSSpeedRef",
+        (Ifd::Gps, 0x0d) => "GPSSpeed",
+        (Ifd::Gps, 0x10) => "GPSImgDirectionRef",
+        (Ifd::Gps, 0x11) => "GPSImgDirection",
+        (Ifd::Gps, 0x12) => "GPSMapDatum",
+        (Ifd::Gps, 0x1d) => "GPSDateStamp",
+        (Ifd::Gps, _) => return None,
+        (Ifd::Interop, 0x01) => "InteroperabilityIndex",
+        (Ifd::Interop, 0x02) => "InteroperabilityVersion",
+        (Ifd::Interop, _) => return None,
+        (_, 0x010e) => "ImageDescription",
+        (_, 0x010f) => "Make",
+        (_, 0x0110) => "Model",
+        (_, 0x0112) => "Orientation",
+        (_, 0x011a) => "XResolution",
+        (_, 0x011b) => "YResolution",
+        (_, 0x0128) => "ResolutionUnit",
+        (_, 0x0131) => "Software",
+        (_, 0x0132) => "DateTime",
+        (_, 0x013b) => "Artist",
+        (_, 0x0201) => "JPEGInterchangeFormat",
+        (_, 0x0202) => "JPEGInterchangeFormatLength",
+        (_, 0x0213) => "YCbCrPositioning",
+        (_, 0x8298) => "Copyright",
+        (_, 0x829a) => "ExposureTime",
+        (_, 0x829d) => "FNumber",
+        (_, 0x8769) => "ExifIFDPointer",
+        (_, 0x8822) => "ExposureProgram",
+        (_, 0x8825) => "GPSInfoIFDPointer",
+        (_, 0x8827) => "ISOSpeedRatings",
+        (_, 0x9000) => "ExifVersion",
+        (_, 0x9003) => "DateTimeOriginal",
+        (_, 0x9004) => "DateTimeDigitized",
+        (_, 0x9010) => "OffsetTime",
+        (_, 0x9101) => "ComponentsConfiguration",
+        (_, 0x9201) => "ShutterSpeedValue",
+        (_, 0x9202) => "ApertureValue",
+        (_, 0x9204) => "ExposureBiasValue",
+        (_, 0x9207) => "MeteringMode",
+        (_, 0x9209) => "Flash",
+        (_, 0x920a) => "FocalLength",
+        (_, 0x927c) => "MakerNote",
+        (_, 0x9286) => "UserComment",
+        (_, 0xa000) => "FlashpixVersion",
+        (_, 0xa001) => "ColorSpace",
+        (_, 0xa002) => "PixelXDimension",
+        (_, 0xa003) => "PixelYDimension",
+        (_, 0xa005) => "InteroperabilityIFDPointer",
+        (_, 0xa402) => "ExposureMode",
+        (_, 0xa403) => "WhiteBalance",
+        (_, 0xa405) => "FocalLengthIn35mmFilm",
+        (_, 0xa406) => "SceneCaptureType",
+        (_, 0xa420) => "ImageUniqueID",
+        (_, 0xa430) => "CameraOwnerName",
+        (_, 0xa431) => "BodySerialNumber",
+        (_, 0xa433) => "LensMake",
+        (_, 0xa434) => "LensModel",
+        (_, 0xa435) => "LensSerialNumber",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub struct ExifCodec;
+
+impl ChunkCodec for ExifCodec {
+    type Value = Exif;
+
+    fn chunk_type(&self) -> ChunkType {
+        ChunkType::from_str(Exif::CHUNK_TYPE).expect("eXIf is a valid chunk type")
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<Exif> {
+        Ok(Exif::try_from(data)?)
+    }
+
+    fn encode(&self, value: &Exif) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes().to_vec())
+    }
+}
+
+#[derive(Debug)]
+pub enum ExifError {
+    InvalidHeader,
+    Truncated(usize),
+    Loop(usize),
+    UnknownFieldType(u16, u16),
+    MissingTag(&'static str),
+    InvalidOrientation(u16),
+}
+
+impl Display for ExifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "Error: eXIf does not start with a TIFF header"),
+            Self::Truncated(offset) => {
+                write!(
+                    f,
+                    "Error: eXIf refers to data past its end at offset {}",
+                    offset
+                )
+            }
+            Self::Loop(offset) => write!(f, "Error: eXIf IFD at offset {} is linked twice", offset),
+            Self::UnknownFieldType(tag, field_type) => write!(
+                f,
+                "Error: eXIf tag 0x{:04x} has unknown field type {}",
+                tag, field_type
+            ),
+            Self::MissingTag(name) => write!(f, "Error: eXIf has no {} tag", name),
+            Self::InvalidOrientation(orientation) => write!(
+                f,
+                "Error: orientation must be a single value from 1 to 8, found {}",
+                orientation
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExifError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IFD0 with Make, Orientation and a GPS pointer, and a GPS IFD with a latitude.
+    fn exif_bytes(big_endian: bool) -> Vec<u8> {
+        let u16 = |value: u16| match big_endian {
+            true => value.to_be_bytes().to_vec(),
+            false => value.to_le_bytes().to_vec(),
+        };
+        let u32 = |value: u32| match big_endian {
+            true => value.to_be_bytes().to_vec(),
+            false => value.to_le_bytes().to_vec(),
+        };
+        let entry = |tag: u16, field_type: u16, count: u32, value: Vec<u8>| {
+            [u16(tag), u16(field_type), u32(count), value].concat()
+        };
+
+        let mut bytes = match big_endian {
+            true => b"MM\0*".to_vec(),
+            false => b"II*\0".to_vec(),
+        };
+        bytes.extend(u32(8));
+        bytes.extend(u16(3));
+        bytes.extend(entry(0x010f, 2, 6, u32(50)));
+        bytes.extend(entry(0x0112, 3, 1, [u16(1), u16(0)].concat()));
+        bytes.extend(entry(0x8825, 4, 1, u32(56)));
+        bytes.extend(u32(0));
+        bytes.extend(b"Canon\0");
+        bytes.extend(u16(2));
+        bytes.extend(entry(0x0001, 2, 2, b"N\0\0\0".to_vec()));
+        bytes.extend(entry(0x0002, 5, 3, u32(86)));
+        bytes.extend(u32(0));
+        for value in [51, 1, 30, 1, 0, 1] {
+            bytes.extend(u32(value));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_entries_in_both_byte_orders() {
+        for big_endian in [false, true] {
+            let exif = Exif::try_from(exif_bytes(big_endian).as_slice()).unwrap();
+            let entries = exif.entries().unwrap();
+            let names: Vec<&str> = entries.iter().filter_map(|e| e.name()).collect();
+            assert_eq!(
+                names,
+                [
+                    "Make",
+                    "Orientation",
+                    "GPSInfoIFDPointer",
+                    "GPSLatitudeRef",
+                    "GPSLatitude"
+                ]
+            );
+            assert_eq!(entries[0].value(), &ExifValue::Ascii("Canon".to_string()));
+            assert_eq!(entries[4].to_string(), "GPS GPSLatitude: 51/1, 30/1, 0/1");
+            assert_eq!(exif.orientation().unwrap(), Some(1));
+        }
+    }
+
+    #[test]
+    fn test_remove_gps() {
+        let mut exif = Exif::try_from(exif_bytes(true).as_slice()).unwrap();
+        assert!(exif.remove_gps().unwrap());
+
+        let entries = exif.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.ifd() == Ifd::Primary));
+        assert!(exif.as_bytes()[56..].iter().all(|&byte| byte == 0));
+        assert!(!exif.remove_gps().unwrap());
+    }
+
+    #[test]
+    fn test_empty_pointer_and_orientation() {
+        let mut bytes = exif_bytes(false);
+        bytes[26..30].copy_from_slice(&0u32.to_le_bytes());
+        bytes[38..42].copy_from_slice(&0u32.to_le_bytes());
+        let mut exif = Exif::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(exif.orientation().unwrap(), None);
+        assert!(exif.remove_gps().unwrap());
+        assert_eq!(exif.entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_set_orientation() {
+        let mut exif = Exif::try_from(exif_bytes(false).as_slice()).unwrap();
+        exif.set_orientation(6).unwrap();
+        assert_eq!(exif.orientation().unwrap(), Some(6));
+        assert_eq!(exif.entries().unwrap().len(), 5);
+        assert!(exif.set_orientation(9).is_err());
+    }
+
+    #[test]
+    fn test_invalid_exif() {
+        assert!(Exif::try_from(&b"JFIF"[..]).is_err());
+        let mut bytes = exif_bytes(false);
+        bytes.truncate(60);
+        let exif = Exif::try_from(bytes.as_slice()).unwrap();
+        assert!(matches!(exif.entries(), Err(ExifError::Truncated(_))));
+    }
+}
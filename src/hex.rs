@@ -0,0 +1,117 @@
+use std::ops::Range;
+use std::{fmt, fmt::Display};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Formats bytes as lines of offset, hex and ASCII columns, like `hexdump -C`.
+///
+/// `range` restricts the dump to a window of the data so large chunks can be paged through.
+/// Offsets are always counted from the start of the data.
+pub struct HexDump<'a> {
+    data: &'a [u8],
+    offset: usize,
+    length: Option<usize>,
+    highlight: Range<usize>,
+}
+
+impl<'a> HexDump<'a> {
+    pub fn new(data: &'a [u8]) -> HexDump<'a> {
+        HexDump {
+            data,
+            offset: 0,
+            length: None,
+            highlight: 0..0,
+        }
+    }
+
+    /// Shows only `length` bytes, or everything up to the end, starting at `offset`.
+    pub fn range(self, offset: usize, length: Option<usize>) -> HexDump<'a> {
+        HexDump {
+            offset,
+            length,
+            ..self
+        }
+    }
+
+    /// Marks `length` bytes from `start` with `^^` under their hex column.
+    pub fn highlight(self, start: usize, length: usize) -> HexDump<'a> {
+        HexDump {
+            highlight: start..start.saturating_add(length),
+            ..self
+        }
+    }
+
+    /// The bytes that will be shown, empty when the offset is past the end of the data.
+    pub fn window(&self) -> &'a [u8] {
+        let start = self.offset.min(self.data.len());
+        let end = match self.length {
+            Some(length) => start.saturating_add(length).min(self.data.len()),
+            None => self.data.len(),
+        };
+        &self.data[start..end]
+    }
+}
+
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (line, bytes) in self.window().chunks(BYTES_PER_LINE).enumerate() {
+            let start = self.offset + line * BYTES_PER_LINE;
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = bytes
+                .iter()
+                .map(|&byte| match byte.is_ascii_graphic() || byte == b' ' {
+                    true => byte as char,
+                    false => '.',
+                })
+                .collect();
+            writeln!(
+                f,
+                "{:08x}  {:<width$}  |{}|",
+                start,
+                hex.join(" "),
+                ascii,
+                width = BYTES_PER_LINE * 3 - 1
+            )?;
+
+            let marks: Vec<&str> = (start..start + bytes.len())
+                .map(|offset| match self.highlight.contains(&offset) {
+                    true => "^^",
+                    false => "  ",
+                })
+                .collect();
+            if marks.contains(&"^^") {
+                writeln!(f, "{:10}{}", "", marks.join(" ").trim_end())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_dump() {
+        let dump = HexDump::new(b"PNG\x00").to_string();
+        assert_eq!(dump, format!("00000000  {:<47}  |PNG.|\n", "50 4e 47 00"));
+    }
+
+    #[test]
+    fn test_hex_dump_range() {
+        let data: Vec<u8> = (0..40).collect();
+        let dump = HexDump::new(&data).range(20, Some(4));
+        assert_eq!(dump.window(), &[20, 21, 22, 23]);
+        assert!(dump.to_string().starts_with("00000014  14 15 16 17 "));
+
+        assert!(HexDump::new(&data).range(50, None).window().is_empty());
+        assert_eq!(HexDump::new(&data).range(36, Some(10)).window().len(), 4);
+    }
+
+    #[test]
+    fn test_hex_dump_highlight() {
+        let dump = HexDump::new(b"PNG\x00").highlight(1, 2).to_string();
+        let marks = dump.lines().nth(1).unwrap();
+        assert_eq!(marks, format!("{:10}   ^^ ^^", ""));
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fmt, fmt::Display};
+
+use crate::chunk::Chunk;
+
+const IHDR_LENGTH: usize = 13;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ColorType {
+    Grayscale,
+    Rgb,
+    Indexed,
+    GrayscaleAlpha,
+    Rgba,
+}
+
+impl ColorType {
+    pub fn channels(&self) -> u32 {
+        match self {
+            Self::Grayscale | Self::Indexed => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+
+    fn allows_bit_depth(&self, bit_depth: u8) -> bool {
+        match self {
+            Self::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            Self::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+            Self::Rgb | Self::GrayscaleAlpha | Self::Rgba => matches!(bit_depth, 8 | 16),
+        }
+    }
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = IhdrError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Grayscale),
+            2 => Ok(Self::Rgb),
+            3 => Ok(Self::Indexed),
+            4 => Ok(Self::GrayscaleAlpha),
+            6 => Ok(Self::Rgba),
+            _ => Err(IhdrError::InvalidColorType(value)),
+        }
+    }
+}
+
+impl From<ColorType> for u8 {
+    fn from(color_type: ColorType) -> u8 {
+        match color_type {
+            ColorType::Grayscale => 0,
+            ColorType::Rgb => 2,
+            ColorType::Indexed => 3,
+            ColorType::GrayscaleAlpha => 4,
+            ColorType::Rgba => 6,
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Grayscale => write!(f, "Grayscale"),
+            Self::Rgb => write!(f, "RGB"),
+            Self::Indexed => write!(f, "Indexed"),
+            Self::GrayscaleAlpha => write!(f, "Grayscale + Alpha"),
+            Self::Rgba => write!(f, "RGBA"),
+        }
+    }
+}
+
+/// The image header, always the first chunk of a PNG.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Ihdr {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+}
+
+impl Ihdr {
+    pub const CHUNK_TYPE: &'static str = "IHDR";
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    pub fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    pub fn interlaced(&self) -> bool {
+        self.interlaced
+    }
+
+    pub fn bits_per_pixel(&self) -> u32 {
+        self.color_type.channels() * self.bit_depth as u32
+    }
+
+    pub fn pixel_count(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// The same header for an image of different dimensions, such as one APNG frame.
+    pub fn with_size(&self, width: u32, height: u32) -> Ihdr {
+        Ihdr {
+            width,
+            height,
+            ..*self
+        }
+    }
+
+    /// The chunk data, with compression and filter method 0 as the only ones defined.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(IHDR_LENGTH);
+        bytes.extend(self.width.to_be_bytes());
+        bytes.extend(self.height.to_be_bytes());
+        bytes.extend([self.bit_depth, self.color_type.into(), 0, 0]);
+        bytes.push(self.interlaced as u8);
+        bytes
+    }
+}
+
+impl TryFrom<&Chunk> for Ihdr {
+    type Error = IhdrError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        let data = chunk.data();
+        if data.len() != IHDR_LENGTH {
+            return Err(IhdrError::InvalidLength(data.len()));
+        }
+
+        let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        if width == 0 || height == 0 {
+            return Err(IhdrError::ZeroDimension);
+        }
+
+        let bit_depth = data[8];
+        let color_type = ColorType::try_from(data[9])?;
+        if !color_type.allows_bit_depth(bit_depth) {
+            return Err(IhdrError::InvalidBitDepth(bit_depth, color_type));
+        }
+
+        let interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            method => return Err(IhdrError::InvalidInterlaceMethod(method)),
+        };
+
+        Ok(Ihdr {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced,
+        })
+    }
+}
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}, {}-bit {}{}",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.color_type,
+            if self.interlaced { ", interlaced" } else { "" }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum IhdrError {
+    InvalidLength(usize),
+    ZeroDimension,
+    InvalidColorType(u8),
+    InvalidBitDepth(u8, ColorType),
+    InvalidInterlaceMethod(u8),
+}
+
+impl Display for IhdrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(
+                f,
+                "Error: IHDR must be {} bytes long, found {} bytes",
+                IHDR_LENGTH, length
+            ),
+            Self::ZeroDimension => write!(f, "Error: IHDR width and height must be non zero"),
+            Self::InvalidColorType(color_type) => {
+                write!(f, "Error: invalid IHDR color type {}", color_type)
+            }
+            Self::InvalidBitDepth(bit_depth, color_type) => write!(
+                f,
+                "Error: bit depth {} is not allowed for color type {}",
+                bit_depth, color_type
+            ),
+            Self::InvalidInterlaceMethod(method) => {
+                write!(f, "Error: invalid IHDR interlace method {}", method)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IhdrError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn ihdr_chunk(data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_ihdr_from_chunk() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50, 0, 0, 0, 40, 8, 6, 0, 0, 0]);
+        let ihdr = Ihdr::try_from(&chunk).unwrap();
+
+        assert_eq!(ihdr.width(), 50);
+        assert_eq!(ihdr.height(), 40);
+        assert_eq!(ihdr.color_type(), ColorType::Rgba);
+        assert_eq!(ihdr.bits_per_pixel(), 32);
+        assert_eq!(ihdr.pixel_count(), 2000);
+        assert!(!ihdr.interlaced());
+    }
+
+    #[test]
+    fn test_ihdr_invalid_bit_depth() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50, 0, 0, 0, 40, 4, 2, 0, 0, 0]);
+        assert!(matches!(
+            Ihdr::try_from(&chunk),
+            Err(IhdrError::InvalidBitDepth(4, ColorType::Rgb))
+        ));
+    }
+
+    #[test]
+    fn test_ihdr_invalid_length() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50]);
+        assert!(Ihdr::try_from(&chunk).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::{fmt, fmt::Display};
+
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::ihdr::Ihdr;
+use crate::png::Png;
+
+/// Starting column, starting row, column step and row step of the seven Adam7 passes.
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// The decompressed and unfiltered scanlines of an image.
+///
+/// Filter type bytes are dropped, so `bytes` holds only packed samples. Interlaced images
+/// keep their Adam7 passes one after the other rather than being reassembled.
+pub struct ImageData {
+    ihdr: Ihdr,
+    bytes: Vec<u8>,
+}
+
+impl ImageData {
+    pub fn ihdr(&self) -> &Ihdr {
+        &self.ihdr
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The low byte of every sample, or `None` for bit depths below 8.
+    pub fn sample_low_bytes(&self) -> Option<Vec<u8>> {
+        match self.ihdr.bit_depth() {
+            8 => Some(self.bytes.clone()),
+            16 => Some(self.bytes.iter().skip(1).step_by(2).copied().collect()),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Png> for ImageData {
+    type Error = ImageDataError;
+
+    fn try_from(png: &Png) -> Result<Self, Self::Error> {
+        let ihdr = png
+            .ihdr()
+            .map_err(|e| ImageDataError::Header(e.to_string()))?;
+
+        let compressed: Vec<u8> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string() == Png::DATA_CHUNK_TYPE)
+            .flat_map(|chunk| chunk.data().iter().copied())
+            .collect();
+        if compressed.is_empty() {
+            return Err(ImageDataError::MissingData);
+        }
+
+        let passes: Vec<(u32, u32)> = match ihdr.interlaced() {
+            true => ADAM7_PASSES
+                .iter()
+                .map(|&(x0, y0, dx, dy)| {
+                    (
+                        pass_size(ihdr.width(), x0, dx),
+                        pass_size(ihdr.height(), y0, dy),
+                    )
+                })
+                .collect(),
+            false => vec![(ihdr.width(), ihdr.height())],
+        };
+        let row_length = |width: u32| (width as usize * ihdr.bits_per_pixel() as usize).div_ceil(8);
+        let pass_length = |(width, height): (u32, u32)| match width == 0 || height == 0 {
+            true => 0,
+            false => (row_length(width) + 1) * height as usize,
+        };
+
+        // IHDR fixes the size of the filtered data, so inflating more than that is refused
+        // rather than letting a small stream expand until memory runs out.
+        let expected = passes.iter().copied().map(pass_length).sum();
+        let filtered = inflate_with_limit(&compressed, expected)?;
+
+        let mut bytes = vec![];
+        let mut offset = 0;
+        for (width, height) in passes {
+            let pass_length = pass_length((width, height));
+            if pass_length == 0 {
+                continue;
+            }
+            let row_length = row_length(width);
+            let pass = filtered
+                .get(offset..offset + pass_length)
+                .ok_or(ImageDataError::Truncated)?;
+            bytes.extend(unfilter(pass, row_length, &ihdr)?);
+            offset += pass_length;
+        }
+
+        Ok(ImageData { ihdr, bytes })
+    }
+}
+
+/// Decompresses a zlib stream such as the concatenated IDAT data.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, ImageDataError> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(data)
+        .map_err(|e| ImageDataError::Inflate(e.to_string()))
+}
+
+/// Decompresses a zlib stream that must not inflate to more than `limit` bytes.
+pub fn inflate_with_limit(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageDataError> {
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, limit).map_err(|e| {
+        match e.status {
+            TINFLStatus::HasMoreOutput => ImageDataError::TooLarge(limit),
+            _ => ImageDataError::Inflate(e.to_string()),
+        }
+    })
+}
+
+fn pass_size(size: u32, start: u32, step: u32) -> u32 {
+    match size > start {
+        true => (size - start).div_ceil(step),
+        false => 0,
+    }
+}
+
+fn unfilter(pass: &[u8], row_length: usize, ihdr: &Ihdr) -> Result<Vec<u8>, ImageDataError> {
+    let pixel_length = (ihdr.bits_per_pixel() as usize).div_ceil(8);
+    let mut previous = vec![0u8; row_length];
+    let mut bytes = Vec::with_capacity(pass.len());
+
+    for line in pass.chunks(row_length + 1) {
+        let (filter, row) = line.split_first().ok_or(ImageDataError::Truncated)?;
+        let mut current = row.to_vec();
+        for i in 0..row_length {
+            let left = if i >= pixel_length {
+                current[i - pixel_length]
+            } else {
+                0
+            };
+            let up = previous[i];
+            let up_left = if i >= pixel_length {
+                previous[i - pixel_length]
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                other => return Err(ImageDataError::InvalidFilter(*other)),
+            };
+            current[i] = current[i].wrapping_add(predictor);
+        }
+        bytes.extend_from_slice(&current);
+        previous = current;
+    }
+
+    Ok(bytes)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageDataError {
+    Header(String),
+    MissingData,
+    Inflate(String),
+    Truncated,
+    TooLarge(usize),
+    InvalidFilter(u8),
+}
+
+impl Display for ImageDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header(header_error) => {
+                write!(f, "Error: invalid image header: {}", header_error)
+            }
+            Self::MissingData => write!(f, "Error: image has no IDAT chunks"),
+            Self::Inflate(inflate_error) => {
+                write!(f, "Error: could not decompress data: {}", inflate_error)
+            }
+            Self::Truncated => write!(f, "Error: image data is shorter than IHDR requires"),
+            Self::TooLarge(limit) => write!(
+                f,
+                "Error: image data inflates to more than the {} bytes IHDR requires",
+                limit
+            ),
+            Self::InvalidFilter(filter) => {
+                write!(f, "Error: invalid scanline filter type {}", filter)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageDataError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::chunk;
+
+    #[test]
+    fn test_unfiltered_image_data() {
+        // 2x2 grayscale, first row unfiltered, second row with the Sub filter
+        let filtered = [0, 10, 20, 1, 30, 5];
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&filtered, 6);
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", vec![0, 0, 0, 2, 0, 0, 0, 2, 8, 0, 0, 0, 0]),
+            chunk("IDAT", compressed),
+            chunk("IEND", vec![]),
+        ]);
+
+        let image_data = ImageData::try_from(&png).unwrap();
+
+        assert_eq!(image_data.bytes(), &[10, 20, 30, 35]);
+    }
+
+    #[test]
+    fn test_refuse_data_larger_than_header() {
+        let bomb = miniz_oxide::deflate::compress_to_vec_zlib(&[0; 1 << 20], 9);
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", vec![0, 0, 0, 2, 0, 0, 0, 2, 8, 0, 0, 0, 0]),
+            chunk("IDAT", bomb),
+            chunk("IEND", vec![]),
+        ]);
+
+        assert!(matches!(
+            ImageData::try_from(&png),
+            Err(ImageDataError::TooLarge(6))
+        ));
+    }
+
+    #[test]
+    fn test_paeth() {
+        assert_eq!(paeth(10, 20, 10), 20);
+        assert_eq!(paeth(20, 10, 10), 20);
+        assert_eq!(paeth(0, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_pass_size() {
+        assert_eq!(pass_size(1, 4, 8), 0);
+        assert_eq!(pass_size(5, 4, 8), 1);
+        assert_eq!(pass_size(16, 0, 8), 2);
+    }
+}
@@ -0,0 +1,31 @@
+pub mod apng;
+pub mod args;
+pub mod atomic;
+pub mod batch;
+pub mod capacity;
+pub mod chunk;
+pub mod chunk_type;
+pub mod codec;
+pub mod color;
+pub mod commands;
+pub mod config;
+pub mod diagnostic;
+pub mod diff;
+pub mod editor;
+pub mod error;
+pub mod exif;
+pub mod hex;
+pub mod ihdr;
+pub mod image_data;
+pub mod palette;
+pub mod physical;
+pub mod png;
+pub mod registry;
+pub mod scan;
+pub mod text;
+
+#[cfg(test)]
+mod testing;
+
+pub use error::{Error, ErrorKind};
+pub type Result<T> = std::result::Result<T, Error>;
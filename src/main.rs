@@ -1,12 +1,66 @@
-mod args;
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
+use std::io::Write;
+use std::thread;
 
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
+use pngme::args::{Action, Arguments};
+use pngme::{batch, commands, Error, Result};
 
-fn main() -> Result<()> {
-    todo!()
+fn main() {
+    if let Err(error) = run() {
+        if !matches!(error, Error::Differs) {
+            eprintln!("Error [{}]: {}", error.code(), error.report());
+        }
+        std::process::exit(error.exit_code());
+    }
+}
+
+fn run() -> Result<()> {
+    let arguments = Arguments::parse_arguments()?;
+    Arguments::action_has_enough_data(&arguments)?;
+
+    let files = batch::expand_inputs(&arguments.inputs(), arguments.recursive())?;
+    if files == [arguments.file_path()] {
+        return commands::run(
+            &arguments,
+            &mut std::io::stdin(),
+            &mut std::io::stdout(),
+            &mut std::io::stderr(),
+        );
+    }
+    match arguments.action() {
+        Action::Transplant => return Err(Error::usage("transplant takes a single source file")),
+        Action::Diff => return Err(Error::usage("diff compares exactly two files")),
+        Action::Edit => return Err(Error::usage("edit works on a single file")),
+        _ => {}
+    }
+
+    let output_directory = arguments.output_path();
+    if let Some(directory) = output_directory {
+        batch::check_outputs(&files, directory)?;
+        std::fs::create_dir_all(directory).map_err(|e| Error::file(directory, e))?;
+    }
+    let jobs = arguments
+        .jobs()
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+
+    let summary = batch::run(
+        &arguments,
+        &files,
+        output_directory,
+        jobs,
+        arguments.fail_fast(),
+    );
+
+    let mut stdout = std::io::stdout();
+    let mut stderr = std::io::stderr();
+    for outcome in summary.outcomes() {
+        stdout.write_all(outcome.output())?;
+        stderr.write_all(outcome.log())?;
+    }
+    eprintln!("{}", summary);
+
+    match summary.into_error() {
+        None => Ok(()),
+        Some(error) => Err(error),
+    }
 }
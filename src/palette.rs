@@ -0,0 +1,660 @@
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkCodec;
+use crate::ihdr::{ColorType, Ihdr};
+use crate::png::Png;
+
+const MAX_PALETTE_ENTRIES: usize = 256;
+const MAX_NAME_LENGTH: usize = 79;
+
+/// The PLTE chunk: up to 256 RGB entries.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Palette {
+    entries: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub const CHUNK_TYPE: &'static str = "PLTE";
+
+    pub fn entries(&self) -> &[[u8; 3]] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.entries.concat()
+    }
+
+    /// Checks that the image's colour type may carry a palette and its bit depth can index it.
+    pub fn check(&self, ihdr: &Ihdr) -> Result<(), PaletteError> {
+        match ihdr.color_type() {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Err(PaletteError::NotAllowed(
+                Self::CHUNK_TYPE,
+                ihdr.color_type(),
+            )),
+            ColorType::Indexed if self.len() > 1 << ihdr.bit_depth() => Err(
+                PaletteError::TooManyEntries(self.len(), 1 << ihdr.bit_depth()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Palette {
+    type Error = PaletteError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.is_empty() || !data.len().is_multiple_of(3) || data.len() / 3 > MAX_PALETTE_ENTRIES
+        {
+            return Err(PaletteError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        let entries = data
+            .chunks(3)
+            .map(|entry| [entry[0], entry[1], entry[2]])
+            .collect();
+        Ok(Palette { entries })
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} entries", self.len())?;
+        for (index, [red, green, blue]) in self.entries.iter().enumerate().take(8) {
+            write!(f, ", #{} {:02x}{:02x}{:02x}", index, red, green, blue)?;
+        }
+        if self.len() > 8 {
+            write!(f, ", ...")?;
+        }
+        Ok(())
+    }
+}
+
+/// The tRNS chunk, whose layout depends on the colour type.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Transparency {
+    /// The grey sample value treated as fully transparent.
+    Gray(u16),
+    /// The RGB sample values treated as fully transparent.
+    Rgb(u16, u16, u16),
+    /// Alpha values for the first palette entries, the rest being opaque.
+    Palette(Vec<u8>),
+}
+
+impl Transparency {
+    pub const CHUNK_TYPE: &'static str = "tRNS";
+
+    pub fn decode(
+        data: &[u8],
+        ihdr: &Ihdr,
+        palette: Option<&Palette>,
+    ) -> Result<Transparency, PaletteError> {
+        let transparency = match ihdr.color_type() {
+            ColorType::Grayscale => {
+                Transparency::Gray(read_samples::<1>(Self::CHUNK_TYPE, data)?[0])
+            }
+            ColorType::Rgb => {
+                let [red, green, blue] = read_samples(Self::CHUNK_TYPE, data)?;
+                Transparency::Rgb(red, green, blue)
+            }
+            ColorType::Indexed => {
+                let palette = palette.ok_or(PaletteError::MissingPalette(Self::CHUNK_TYPE))?;
+                if data.len() > palette.len() {
+                    return Err(PaletteError::TooManyAlphas(data.len(), palette.len()));
+                }
+                Transparency::Palette(data.to_vec())
+            }
+            color_type => return Err(PaletteError::NotAllowed(Self::CHUNK_TYPE, color_type)),
+        };
+        check_samples(Self::CHUNK_TYPE, &transparency.samples(), ihdr)?;
+        Ok(transparency)
+    }
+
+    fn samples(&self) -> Vec<u16> {
+        match self {
+            Self::Gray(gray) => vec![*gray],
+            Self::Rgb(red, green, blue) => vec![*red, *green, *blue],
+            Self::Palette(_) => vec![],
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Palette(alphas) => alphas.clone(),
+            _ => self
+                .samples()
+                .iter()
+                .flat_map(|s| s.to_be_bytes())
+                .collect(),
+        }
+    }
+}
+
+impl Display for Transparency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gray(gray) => write!(f, "grey {} is transparent", gray),
+            Self::Rgb(red, green, blue) => {
+                write!(f, "rgb({}, {}, {}) is transparent", red, green, blue)
+            }
+            Self::Palette(alphas) => write!(f, "alpha for {} palette entries", alphas.len()),
+        }
+    }
+}
+
+/// The bKGD chunk, whose layout depends on the colour type.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Background {
+    Gray(u16),
+    Rgb(u16, u16, u16),
+    PaletteIndex(u8),
+}
+
+impl Background {
+    pub const CHUNK_TYPE: &'static str = "bKGD";
+
+    pub fn decode(
+        data: &[u8],
+        ihdr: &Ihdr,
+        palette: Option<&Palette>,
+    ) -> Result<Background, PaletteError> {
+        let background = match ihdr.color_type() {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+                Background::Gray(read_samples::<1>(Self::CHUNK_TYPE, data)?[0])
+            }
+            ColorType::Rgb | ColorType::Rgba => {
+                let [red, green, blue] = read_samples(Self::CHUNK_TYPE, data)?;
+                Background::Rgb(red, green, blue)
+            }
+            ColorType::Indexed => {
+                let palette = palette.ok_or(PaletteError::MissingPalette(Self::CHUNK_TYPE))?;
+                let &[index] = data else {
+                    return Err(PaletteError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+                };
+                if index as usize >= palette.len() {
+                    return Err(PaletteError::IndexOutOfRange(
+                        Self::CHUNK_TYPE,
+                        index as usize,
+                        palette.len(),
+                    ));
+                }
+                Background::PaletteIndex(index)
+            }
+        };
+        check_samples(Self::CHUNK_TYPE, &background.samples(), ihdr)?;
+        Ok(background)
+    }
+
+    fn samples(&self) -> Vec<u16> {
+        match *self {
+            Self::Gray(gray) => vec![gray],
+            Self::Rgb(red, green, blue) => vec![red, green, blue],
+            Self::PaletteIndex(_) => vec![],
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::PaletteIndex(index) => vec![*index],
+            _ => self
+                .samples()
+                .iter()
+                .flat_map(|s| s.to_be_bytes())
+                .collect(),
+        }
+    }
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gray(gray) => write!(f, "grey {}", gray),
+            Self::Rgb(red, green, blue) => write!(f, "rgb({}, {}, {})", red, green, blue),
+            Self::PaletteIndex(index) => write!(f, "palette entry #{}", index),
+        }
+    }
+}
+
+/// The hIST chunk: how often each palette entry is used.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Histogram {
+    frequencies: Vec<u16>,
+}
+
+impl Histogram {
+    pub const CHUNK_TYPE: &'static str = "hIST";
+
+    pub fn decode(data: &[u8], palette: Option<&Palette>) -> Result<Histogram, PaletteError> {
+        let palette = palette.ok_or(PaletteError::MissingPalette(Self::CHUNK_TYPE))?;
+        if data.len() != palette.len() * 2 {
+            return Err(PaletteError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        let frequencies = data
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        Ok(Histogram { frequencies })
+    }
+
+    pub fn frequencies(&self) -> &[u16] {
+        &self.frequencies
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.frequencies
+            .iter()
+            .flat_map(|f| f.to_be_bytes())
+            .collect()
+    }
+}
+
+impl Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unused = self.frequencies.iter().filter(|&&f| f == 0).count();
+        write!(f, "{} entries, {} unused", self.frequencies.len(), unused)
+    }
+}
+
+/// One colour of a suggested palette, with samples widened to 16 bits when the depth is 16.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct SuggestedEntry {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+    pub frequency: u16,
+}
+
+/// The sPLT chunk: a named palette for viewers that cannot show every colour.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SuggestedPalette {
+    name: String,
+    depth: u8,
+    entries: Vec<SuggestedEntry>,
+}
+
+impl SuggestedPalette {
+    pub const CHUNK_TYPE: &'static str = "sPLT";
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The sample depth of the entries, 8 or 16.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn entries(&self) -> &[SuggestedEntry] {
+        &self.entries
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.name.chars().map(|c| c as u8).collect();
+        bytes.extend([0, self.depth]);
+        for entry in &self.entries {
+            for sample in [entry.red, entry.green, entry.blue, entry.alpha] {
+                match self.depth {
+                    8 => bytes.push(sample as u8),
+                    _ => bytes.extend(sample.to_be_bytes()),
+                }
+            }
+            bytes.extend(entry.frequency.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for SuggestedPalette {
+    type Error = PaletteError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let separator = data
+            .iter()
+            .position(|&byte| byte == 0)
+            .filter(|&length| (1..=MAX_NAME_LENGTH).contains(&length))
+            .ok_or(PaletteError::InvalidName)?;
+        let name = data[..separator].iter().map(|&b| b as char).collect();
+        let (&depth, entries) = data[separator + 1..]
+            .split_first()
+            .ok_or(PaletteError::InvalidLength(Self::CHUNK_TYPE, data.len()))?;
+        let sample_bytes = match depth {
+            8 => 1,
+            16 => 2,
+            _ => return Err(PaletteError::InvalidDepth(depth)),
+        };
+        let entry_length = sample_bytes * 4 + 2;
+        if entries.len() % entry_length != 0 {
+            return Err(PaletteError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+
+        let entries = entries
+            .chunks(entry_length)
+            .map(|entry| {
+                let sample = |index: usize| match sample_bytes {
+                    1 => entry[index] as u16,
+                    _ => u16::from_be_bytes([entry[index * 2], entry[index * 2 + 1]]),
+                };
+                SuggestedEntry {
+                    red: sample(0),
+                    green: sample(1),
+                    blue: sample(2),
+                    alpha: sample(3),
+                    frequency: u16::from_be_bytes([
+                        entry[entry_length - 2],
+                        entry[entry_length - 1],
+                    ]),
+                }
+            })
+            .collect();
+        Ok(SuggestedPalette {
+            name,
+            depth,
+            entries,
+        })
+    }
+}
+
+impl Display for SuggestedPalette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}, {} entries of {}-bit samples",
+            self.name,
+            self.entries.len(),
+            self.depth
+        )
+    }
+}
+
+/// The palette-related chunks of an image, decoded and checked against its header.
+///
+/// Only the first chunk of each single-instance type is decoded, as a decoder would.
+pub struct PaletteChunks {
+    palette: Option<Palette>,
+    transparency: Option<Transparency>,
+    background: Option<Background>,
+    histogram: Option<Histogram>,
+    suggested: Vec<SuggestedPalette>,
+}
+
+impl PaletteChunks {
+    pub fn of(png: &Png) -> crate::Result<PaletteChunks> {
+        let ihdr = png.ihdr()?;
+        let data = |chunk_type: &str| png.chunk_by_type(chunk_type).map(|chunk| chunk.data());
+
+        let palette = data(Palette::CHUNK_TYPE)
+            .map(Palette::try_from)
+            .transpose()?;
+        if let Some(palette) = &palette {
+            palette.check(&ihdr)?;
+        } else if ihdr.color_type() == ColorType::Indexed {
+            return Err(PaletteError::MissingPalette(Ihdr::CHUNK_TYPE).into());
+        }
+        let transparency = data(Transparency::CHUNK_TYPE)
+            .map(|data| Transparency::decode(data, &ihdr, palette.as_ref()))
+            .transpose()?;
+        let background = data(Background::CHUNK_TYPE)
+            .map(|data| Background::decode(data, &ihdr, palette.as_ref()))
+            .transpose()?;
+        let histogram = data(Histogram::CHUNK_TYPE)
+            .map(|data| Histogram::decode(data, palette.as_ref()))
+            .transpose()?;
+
+        let mut suggested: Vec<SuggestedPalette> = vec![];
+        for chunk in png.chunks() {
+            if chunk.chunk_type().to_string() != SuggestedPalette::CHUNK_TYPE {
+                continue;
+            }
+            let palette = SuggestedPalette::try_from(chunk.data())?;
+            if suggested.iter().any(|other| other.name == palette.name) {
+                return Err(PaletteError::DuplicateName(palette.name).into());
+            }
+            suggested.push(palette);
+        }
+
+        Ok(PaletteChunks {
+            palette,
+            transparency,
+            background,
+            histogram,
+            suggested,
+        })
+    }
+
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    pub fn transparency(&self) -> Option<&Transparency> {
+        self.transparency.as_ref()
+    }
+
+    pub fn background(&self) -> Option<&Background> {
+        self.background.as_ref()
+    }
+
+    pub fn histogram(&self) -> Option<&Histogram> {
+        self.histogram.as_ref()
+    }
+
+    pub fn suggested(&self) -> &[SuggestedPalette] {
+        &self.suggested
+    }
+}
+
+/// Reads `N` big-endian 16-bit samples, the only layout tRNS and bKGD use outside indexed
+/// images.
+fn read_samples<const N: usize>(
+    chunk_type: &'static str,
+    data: &[u8],
+) -> Result<[u16; N], PaletteError> {
+    if data.len() != N * 2 {
+        return Err(PaletteError::InvalidLength(chunk_type, data.len()));
+    }
+    let mut samples = [0; N];
+    for (sample, pair) in samples.iter_mut().zip(data.chunks(2)) {
+        *sample = u16::from_be_bytes([pair[0], pair[1]]);
+    }
+    Ok(samples)
+}
+
+fn check_samples(
+    chunk_type: &'static str,
+    samples: &[u16],
+    ihdr: &Ihdr,
+) -> Result<(), PaletteError> {
+    let limit = 1u32 << ihdr.bit_depth();
+    match samples.iter().find(|&&sample| sample as u32 >= limit) {
+        Some(&sample) => Err(PaletteError::SampleOutOfRange(
+            chunk_type,
+            sample,
+            ihdr.bit_depth(),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn chunk_type(name: &str) -> ChunkType {
+    ChunkType::from_str(name).expect("palette chunk types are valid")
+}
+
+/// Reads and writes PLTE chunks, which decode without the image header.
+pub struct PaletteCodec;
+
+impl ChunkCodec for PaletteCodec {
+    type Value = Palette;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(Palette::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<Palette> {
+        Ok(Palette::try_from(data)?)
+    }
+
+    fn encode(&self, value: &Palette) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+pub struct SuggestedPaletteCodec;
+
+impl ChunkCodec for SuggestedPaletteCodec {
+    type Value = SuggestedPalette;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(SuggestedPalette::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<SuggestedPalette> {
+        Ok(SuggestedPalette::try_from(data)?)
+    }
+
+    fn encode(&self, value: &SuggestedPalette) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+#[derive(Debug)]
+pub enum PaletteError {
+    InvalidLength(&'static str, usize),
+    NotAllowed(&'static str, ColorType),
+    MissingPalette(&'static str),
+    TooManyEntries(usize, usize),
+    TooManyAlphas(usize, usize),
+    IndexOutOfRange(&'static str, usize, usize),
+    SampleOutOfRange(&'static str, u16, u8),
+    InvalidName,
+    InvalidDepth(u8),
+    DuplicateName(String),
+}
+
+impl Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(chunk_type, length) => {
+                write!(
+                    f,
+                    "Error: {} chunk of invalid length {}",
+                    chunk_type, length
+                )
+            }
+            Self::NotAllowed(chunk_type, color_type) => write!(
+                f,
+                "Error: {} is not allowed in {} images",
+                chunk_type, color_type
+            ),
+            Self::MissingPalette(chunk_type) => {
+                write!(f, "Error: {} requires a PLTE chunk", chunk_type)
+            }
+            Self::TooManyEntries(entries, max) => write!(
+                f,
+                "Error: PLTE has {} entries but the bit depth allows {}",
+                entries, max
+            ),
+            Self::TooManyAlphas(alphas, entries) => write!(
+                f,
+                "Error: tRNS has {} alpha values but PLTE has {} entries",
+                alphas, entries
+            ),
+            Self::IndexOutOfRange(chunk_type, index, entries) => write!(
+                f,
+                "Error: {} refers to palette entry {} but PLTE has {} entries",
+                chunk_type, index, entries
+            ),
+            Self::SampleOutOfRange(chunk_type, sample, bit_depth) => write!(
+                f,
+                "Error: {} sample {} does not fit in {} bits",
+                chunk_type, sample, bit_depth
+            ),
+            Self::InvalidName => write!(
+                f,
+                "Error: sPLT name must be 1 to 79 Latin-1 characters followed by a null byte"
+            ),
+            Self::InvalidDepth(depth) => {
+                write!(
+                    f,
+                    "Error: sPLT sample depth must be 8 or 16, found {}",
+                    depth
+                )
+            }
+            Self::DuplicateName(name) => {
+                write!(f, "Error: more than one sPLT chunk is named {:?}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ihdr;
+
+    #[test]
+    fn test_palette() {
+        let palette = Palette::try_from(&[255, 0, 0, 0, 255, 0, 0, 0, 255][..]).unwrap();
+        assert_eq!(palette.entries()[1], [0, 255, 0]);
+        assert!(palette.check(&ihdr(2, 3)).is_ok());
+        assert!(matches!(
+            palette.check(&ihdr(1, 3)),
+            Err(PaletteError::TooManyEntries(3, 2))
+        ));
+        assert!(palette.check(&ihdr(8, 0)).is_err());
+        assert!(Palette::try_from(&[1, 2][..]).is_err());
+    }
+
+    #[test]
+    fn test_transparency_forms() {
+        let palette = Palette::try_from(&[0; 6][..]).unwrap();
+        let alphas = Transparency::decode(&[0, 128], &ihdr(8, 3), Some(&palette)).unwrap();
+        assert_eq!(alphas, Transparency::Palette(vec![0, 128]));
+        assert!(matches!(
+            Transparency::decode(&[0, 0, 0], &ihdr(8, 3), Some(&palette)),
+            Err(PaletteError::TooManyAlphas(3, 2))
+        ));
+
+        let gray = Transparency::decode(&[0, 3], &ihdr(2, 0), None).unwrap();
+        assert_eq!(gray.as_bytes(), [0, 3]);
+        assert!(Transparency::decode(&[0, 4], &ihdr(2, 0), None).is_err());
+        let rgb = Transparency::decode(&[0, 1, 0, 2, 0, 3], &ihdr(8, 2), None).unwrap();
+        assert_eq!(rgb, Transparency::Rgb(1, 2, 3));
+        assert!(Transparency::decode(&[0, 1], &ihdr(8, 6), None).is_err());
+    }
+
+    #[test]
+    fn test_background_and_histogram() {
+        let palette = Palette::try_from(&[0; 6][..]).unwrap();
+        let background = Background::decode(&[1], &ihdr(8, 3), Some(&palette)).unwrap();
+        assert_eq!(background, Background::PaletteIndex(1));
+        assert!(Background::decode(&[2], &ihdr(8, 3), Some(&palette)).is_err());
+
+        let histogram = Histogram::decode(&[0, 5, 0, 0], Some(&palette)).unwrap();
+        assert_eq!(histogram.to_string(), "2 entries, 1 unused");
+        assert!(Histogram::decode(&[0, 5], Some(&palette)).is_err());
+        assert!(Histogram::decode(&[0, 5, 0, 0], None).is_err());
+    }
+
+    #[test]
+    fn test_suggested_palette() {
+        let mut data = b"web\0\x08".to_vec();
+        data.extend([10, 20, 30, 255, 0, 7]);
+        let palette = SuggestedPalette::try_from(data.as_slice()).unwrap();
+        assert_eq!(palette.entries()[0].blue, 30);
+        assert_eq!(palette.entries()[0].frequency, 7);
+        assert_eq!(palette.as_bytes(), data);
+
+        data[4] = 16;
+        assert!(SuggestedPalette::try_from(data.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,551 @@
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fmt, fmt::Display};
+
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkCodec;
+use crate::ihdr::{ColorType, Ihdr};
+
+const METRES_PER_INCH: f64 = 0.0254;
+/// PNG integers stop at 2^31 - 1.
+const MAX_PIXELS_PER_METRE: f64 = ((1u32 << 31) - 1) as f64;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The pHYs chunk: pixels per unit along each axis, in metres or of unknown unit.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct PixelDimensions {
+    x: u32,
+    y: u32,
+    in_metres: bool,
+}
+
+impl PixelDimensions {
+    pub const CHUNK_TYPE: &'static str = "pHYs";
+
+    /// Square pixels at the given dots per inch, stored as a whole number of pixels per metre
+    /// that must be at least 1.
+    pub fn from_dpi(dpi: f64) -> Result<PixelDimensions, PhysicalError> {
+        let pixels_per_metre = (dpi / METRES_PER_INCH).round();
+        if !(1.0..=MAX_PIXELS_PER_METRE).contains(&pixels_per_metre) {
+            return Err(PhysicalError::InvalidDensity(dpi));
+        }
+        Ok(PixelDimensions {
+            x: pixels_per_metre as u32,
+            y: pixels_per_metre as u32,
+            in_metres: true,
+        })
+    }
+
+    pub fn pixels_per_unit(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// Dots per inch along each axis, or `None` when only the aspect ratio is known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        let dpi = |pixels_per_metre: u32| pixels_per_metre as f64 * METRES_PER_INCH;
+        self.in_metres.then_some((dpi(self.x), dpi(self.y)))
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = [self.x.to_be_bytes(), self.y.to_be_bytes()].concat();
+        bytes.push(self.in_metres as u8);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for PixelDimensions {
+    type Error = PhysicalError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != 9 {
+            return Err(PhysicalError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        let in_metres = match data[8] {
+            0 => false,
+            1 => true,
+            unit => return Err(PhysicalError::InvalidUnit(Self::CHUNK_TYPE, unit)),
+        };
+        Ok(PixelDimensions {
+            x: read_u32(data, 0),
+            y: read_u32(data, 4),
+            in_metres,
+        })
+    }
+}
+
+impl Display for PixelDimensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dpi() {
+            Some((x, _)) if self.x == self.y => write!(f, "{:.0} dpi", x),
+            Some((x, y)) => write!(f, "{:.0} x {:.0} dpi", x, y),
+            None => write!(f, "aspect ratio {}:{}", self.x, self.y),
+        }
+    }
+}
+
+/// The tIME chunk: when the image was last modified, in UTC.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct ModificationTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl ModificationTime {
+    pub const CHUNK_TYPE: &'static str = "tIME";
+
+    pub fn now() -> ModificationTime {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        ModificationTime::from_unix(seconds)
+    }
+
+    /// The calendar time `seconds` after the Unix epoch.
+    pub fn from_unix(seconds: u64) -> ModificationTime {
+        let (days, time) = (seconds / SECONDS_PER_DAY, seconds % SECONDS_PER_DAY);
+
+        // Converts days since the epoch to a proleptic Gregorian date, counting eras of
+        // 400 years from March 1st so leap days fall at the end of each year.
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        ModificationTime {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.year.to_be_bytes().to_vec();
+        bytes.extend([self.month, self.day, self.hour, self.minute, self.second]);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for ModificationTime {
+    type Error = PhysicalError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let &[high, low, month, day, hour, minute, second] = data else {
+            return Err(PhysicalError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        };
+        let time = ModificationTime {
+            year: u16::from_be_bytes([high, low]),
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        };
+        let valid = (1..=12).contains(&month)
+            && (1..=31).contains(&day)
+            && hour <= 23
+            && minute <= 59
+            // 60 allows for leap seconds.
+            && second <= 60;
+        match valid {
+            true => Ok(time),
+            false => Err(PhysicalError::InvalidTime(time.to_string())),
+        }
+    }
+}
+
+impl Display for ModificationTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// The oFFs chunk: where the image sits on a page, in pixels or micrometres.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Offsets {
+    x: i32,
+    y: i32,
+    in_micrometres: bool,
+}
+
+impl Offsets {
+    pub const CHUNK_TYPE: &'static str = "oFFs";
+
+    pub fn position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = [self.x.to_be_bytes(), self.y.to_be_bytes()].concat();
+        bytes.push(self.in_micrometres as u8);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for Offsets {
+    type Error = PhysicalError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != 9 {
+            return Err(PhysicalError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        let in_micrometres = match data[8] {
+            0 => false,
+            1 => true,
+            unit => return Err(PhysicalError::InvalidUnit(Self::CHUNK_TYPE, unit)),
+        };
+        Ok(Offsets {
+            x: read_u32(data, 0) as i32,
+            y: read_u32(data, 4) as i32,
+            in_micrometres,
+        })
+    }
+}
+
+impl Display for Offsets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = if self.in_micrometres { "µm" } else { "px" };
+        write!(f, "offset ({}, {}) {}", self.x, self.y, unit)
+    }
+}
+
+/// The sCAL chunk: the size of one pixel in the photographed subject, kept as the decimal
+/// strings the chunk stores so that writing it back is lossless.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PhysicalScale {
+    in_radians: bool,
+    width: String,
+    height: String,
+}
+
+impl PhysicalScale {
+    pub const CHUNK_TYPE: &'static str = "sCAL";
+
+    pub fn width(&self) -> f64 {
+        self.width.parse().expect("checked when decoded")
+    }
+
+    pub fn height(&self) -> f64 {
+        self.height.parse().expect("checked when decoded")
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![if self.in_radians { 2 } else { 1 }];
+        bytes.extend(self.width.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.height.as_bytes());
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for PhysicalScale {
+    type Error = PhysicalError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let (&unit, values) = data
+            .split_first()
+            .ok_or(PhysicalError::InvalidLength(Self::CHUNK_TYPE, 0))?;
+        let in_radians = match unit {
+            1 => false,
+            2 => true,
+            unit => return Err(PhysicalError::InvalidUnit(Self::CHUNK_TYPE, unit)),
+        };
+        let mut parts = values.split(|&byte| byte == 0);
+        let mut value = || -> Result<String, PhysicalError> {
+            let text = parts
+                .next()
+                .and_then(|part| std::str::from_utf8(part).ok())
+                .ok_or(PhysicalError::InvalidScale)?;
+            match text.parse::<f64>() {
+                Ok(number) if number > 0.0 && !text.starts_with('+') => Ok(text.to_string()),
+                _ => Err(PhysicalError::InvalidScale),
+            }
+        };
+        let (width, height) = (value()?, value()?);
+        if parts.next().is_some() {
+            return Err(PhysicalError::InvalidScale);
+        }
+        Ok(PhysicalScale {
+            in_radians,
+            width,
+            height,
+        })
+    }
+}
+
+impl Display for PhysicalScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = if self.in_radians { "rad" } else { "m" };
+        write!(f, "pixel is {} x {} {}", self.width, self.height, unit)
+    }
+}
+
+/// The sBIT chunk: how many bits of each channel carry the original image's precision.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SignificantBits {
+    bits: Vec<u8>,
+}
+
+impl SignificantBits {
+    pub const CHUNK_TYPE: &'static str = "sBIT";
+
+    /// Decodes the chunk for an image with this header, which decides how many channels
+    /// it holds and how many bits each may claim.
+    pub fn decode(data: &[u8], ihdr: &Ihdr) -> Result<SignificantBits, PhysicalError> {
+        let (channels, max_bits) = match ihdr.color_type() {
+            ColorType::Indexed => (3, 8),
+            color_type => (color_type.channels() as usize, ihdr.bit_depth()),
+        };
+        if data.len() != channels {
+            return Err(PhysicalError::InvalidLength(Self::CHUNK_TYPE, data.len()));
+        }
+        if let Some(&bits) = data.iter().find(|&&bits| bits == 0 || bits > max_bits) {
+            return Err(PhysicalError::InvalidSignificantBits(bits, max_bits));
+        }
+        Ok(SignificantBits {
+            bits: data.to_vec(),
+        })
+    }
+
+    /// The significant bits of each channel, in the order the pixels store them.
+    pub fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+}
+
+impl Display for SignificantBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits: Vec<String> = self.bits.iter().map(|bits| bits.to_string()).collect();
+        write!(f, "{} significant bits", bits.join("/"))
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn chunk_type(name: &str) -> ChunkType {
+    ChunkType::from_str(name).expect("physical chunk types are valid")
+}
+
+pub struct PixelDimensionsCodec;
+
+impl ChunkCodec for PixelDimensionsCodec {
+    type Value = PixelDimensions;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(PixelDimensions::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<PixelDimensions> {
+        Ok(PixelDimensions::try_from(data)?)
+    }
+
+    fn encode(&self, value: &PixelDimensions) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+pub struct ModificationTimeCodec;
+
+impl ChunkCodec for ModificationTimeCodec {
+    type Value = ModificationTime;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(ModificationTime::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<ModificationTime> {
+        Ok(ModificationTime::try_from(data)?)
+    }
+
+    fn encode(&self, value: &ModificationTime) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+pub struct OffsetsCodec;
+
+impl ChunkCodec for OffsetsCodec {
+    type Value = Offsets;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(Offsets::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<Offsets> {
+        Ok(Offsets::try_from(data)?)
+    }
+
+    fn encode(&self, value: &Offsets) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+pub struct PhysicalScaleCodec;
+
+impl ChunkCodec for PhysicalScaleCodec {
+    type Value = PhysicalScale;
+
+    fn chunk_type(&self) -> ChunkType {
+        chunk_type(PhysicalScale::CHUNK_TYPE)
+    }
+
+    fn decode(&self, data: &[u8]) -> crate::Result<PhysicalScale> {
+        Ok(PhysicalScale::try_from(data)?)
+    }
+
+    fn encode(&self, value: &PhysicalScale) -> crate::Result<Vec<u8>> {
+        Ok(value.as_bytes())
+    }
+}
+
+#[derive(Debug)]
+pub enum PhysicalError {
+    InvalidLength(&'static str, usize),
+    InvalidUnit(&'static str, u8),
+    InvalidTime(String),
+    InvalidScale,
+    InvalidSignificantBits(u8, u8),
+    InvalidDensity(f64),
+}
+
+impl Display for PhysicalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(chunk_type, length) => {
+                write!(
+                    f,
+                    "Error: {} chunk of invalid length {}",
+                    chunk_type, length
+                )
+            }
+            Self::InvalidUnit(chunk_type, unit) => {
+                write!(f, "Error: invalid {} unit specifier {}", chunk_type, unit)
+            }
+            Self::InvalidTime(time) => write!(f, "Error: tIME holds an invalid time {}", time),
+            Self::InvalidScale => write!(
+                f,
+                "Error: sCAL must hold two positive numbers separated by a null byte"
+            ),
+            Self::InvalidSignificantBits(bits, max_bits) => write!(
+                f,
+                "Error: sBIT value {} is not between 1 and the sample depth {}",
+                bits, max_bits
+            ),
+            Self::InvalidDensity(dpi) => write!(
+                f,
+                "Error: {} dpi is not a pixel density PNG can store, give a positive number up to about 54 million",
+                dpi
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PhysicalError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+
+    #[test]
+    fn test_pixel_dimensions_dpi() {
+        let dimensions = PixelDimensions::from_dpi(300.0).unwrap();
+        assert_eq!(dimensions.pixels_per_unit(), (11811, 11811));
+        assert_eq!(dimensions.to_string(), "300 dpi");
+
+        let decoded = PixelDimensions::try_from(dimensions.as_bytes().as_slice()).unwrap();
+        assert_eq!(decoded, dimensions);
+
+        let aspect = PixelDimensions::try_from(&[0, 0, 0, 2, 0, 0, 0, 1, 0][..]).unwrap();
+        assert_eq!(aspect.dpi(), None);
+        assert!(PixelDimensions::try_from(&[0, 0, 0, 2, 0, 0, 0, 1, 2][..]).is_err());
+
+        for dpi in [0.0, -300.0, f64::NAN, f64::INFINITY, 1e12] {
+            assert!(PixelDimensions::from_dpi(dpi).is_err());
+        }
+    }
+
+    #[test]
+    fn test_modification_time() {
+        let time = ModificationTime::from_unix(951_825_600);
+        assert_eq!(time.to_string(), "2000-02-29T12:00:00Z");
+        assert_eq!(
+            ModificationTime::from_unix(0).to_string(),
+            "1970-01-01T00:00:00Z"
+        );
+
+        let decoded = ModificationTime::try_from(time.as_bytes().as_slice()).unwrap();
+        assert_eq!(decoded, time);
+        assert!(ModificationTime::try_from(&[7, 208, 13, 1, 0, 0, 0][..]).is_err());
+    }
+
+    #[test]
+    fn test_offsets_and_scale() {
+        let offsets = Offsets::try_from(&[255, 255, 255, 246, 0, 0, 0, 5, 0][..]).unwrap();
+        assert_eq!(offsets.position(), (-10, 5));
+        assert_eq!(offsets.as_bytes(), [255, 255, 255, 246, 0, 0, 0, 5, 0]);
+
+        let scale = PhysicalScale::try_from(&b"\x010.001\x001.5e-3"[..]).unwrap();
+        assert_eq!(scale.height(), 0.0015);
+        assert_eq!(scale.as_bytes(), b"\x010.001\x001.5e-3");
+        assert!(PhysicalScale::try_from(&b"\x01-1\x001"[..]).is_err());
+        assert!(PhysicalScale::try_from(&b"\x011"[..]).is_err());
+    }
+
+    #[test]
+    fn test_significant_bits() {
+        let header = Chunk::new(
+            chunk_type(Ihdr::CHUNK_TYPE),
+            vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0],
+        );
+        let ihdr = Ihdr::try_from(&header).unwrap();
+        assert_eq!(
+            SignificantBits::decode(&[5, 6, 5], &ihdr)
+                .unwrap()
+                .to_string(),
+            "5/6/5 significant bits"
+        );
+        assert!(SignificantBits::decode(&[5, 6], &ihdr).is_err());
+        assert!(SignificantBits::decode(&[5, 9, 5], &ihdr).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::{fmt, fmt::Display};
+
+/// Where a chunk must sit in the chunk list regardless of the other chunks present.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Position {
+    First,
+    Last,
+    Anywhere,
+}
+
+/// What the PNG, APNG and extension specifications say about one registered chunk type.
+#[derive(Debug)]
+pub struct ChunkInfo {
+    name: &'static str,
+    description: &'static str,
+    multiple: bool,
+    position: Position,
+    after: &'static [&'static str],
+    before: &'static [&'static str],
+    compressed: bool,
+}
+
+impl ChunkInfo {
+    const fn new(name: &'static str, description: &'static str) -> ChunkInfo {
+        ChunkInfo {
+            name,
+            description,
+            multiple: false,
+            position: Position::Anywhere,
+            after: &[],
+            before: &[],
+            compressed: false,
+        }
+    }
+
+    const fn multiple(self) -> ChunkInfo {
+        ChunkInfo {
+            multiple: true,
+            ..self
+        }
+    }
+
+    const fn at(self, position: Position) -> ChunkInfo {
+        ChunkInfo { position, ..self }
+    }
+
+    const fn after(self, after: &'static [&'static str]) -> ChunkInfo {
+        ChunkInfo { after, ..self }
+    }
+
+    const fn before(self, before: &'static [&'static str]) -> ChunkInfo {
+        ChunkInfo { before, ..self }
+    }
+
+    const fn compressed(self) -> ChunkInfo {
+        ChunkInfo {
+            compressed: true,
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Whether more than one chunk of this type may appear in an image.
+    pub fn allows_multiple(&self) -> bool {
+        self.multiple
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Chunk types which, when present, must come before this one.
+    pub fn must_follow(&self) -> &'static [&'static str] {
+        self.after
+    }
+
+    /// Chunk types which, when present, must come after this one.
+    pub fn must_precede(&self) -> &'static [&'static str] {
+        self.before
+    }
+
+    /// Whether the chunk's data is always deflated, so it is expected to look random. iTXt is
+    /// not, since its text is only compressed when its compression flag is set.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+}
+
+impl Display for ChunkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.description)?;
+
+        let mut constraints = vec![];
+        match self.position {
+            Position::First => constraints.push("must be first".to_string()),
+            Position::Last => constraints.push("must be last".to_string()),
+            Position::Anywhere => {}
+        }
+        if !self.after.is_empty() {
+            constraints.push(format!("must follow {}", self.after.join("/")));
+        }
+        if !self.before.is_empty() {
+            constraints.push(format!("must precede {}", self.before.join("/")));
+        }
+        if !constraints.is_empty() {
+            write!(f, "; {}", constraints.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+const BEFORE_PALETTE: &[&str] = &["PLTE", "IDAT"];
+const BEFORE_DATA: &[&str] = &["IDAT"];
+const AFTER_PALETTE: &[&str] = &["PLTE"];
+const AFTER_DATA: &[&str] = &["IDAT"];
+
+/// Every registered chunk type, critical chunks first.
+pub static KNOWN_CHUNKS: [ChunkInfo; 34] = [
+    ChunkInfo::new("IHDR", "image header").at(Position::First),
+    ChunkInfo::new("PLTE", "palette").before(BEFORE_DATA),
+    ChunkInfo::new("IDAT", "image data").multiple().compressed(),
+    ChunkInfo::new("IEND", "image trailer").at(Position::Last),
+    ChunkInfo::new("cHRM", "primary chromaticities and white point").before(BEFORE_PALETTE),
+    ChunkInfo::new("gAMA", "image gamma").before(BEFORE_PALETTE),
+    ChunkInfo::new("iCCP", "embedded ICC profile")
+        .before(BEFORE_PALETTE)
+        .compressed(),
+    ChunkInfo::new("sBIT", "significant bits").before(BEFORE_PALETTE),
+    ChunkInfo::new("sRGB", "standard RGB colour space").before(BEFORE_PALETTE),
+    ChunkInfo::new("cICP", "coding-independent code points").before(BEFORE_PALETTE),
+    ChunkInfo::new("mDCV", "mastering display colour volume").before(BEFORE_DATA),
+    ChunkInfo::new("cLLI", "content light level information").before(BEFORE_DATA),
+    ChunkInfo::new("tEXt", "textual data").multiple(),
+    ChunkInfo::new("zTXt", "compressed textual data")
+        .multiple()
+        .compressed(),
+    ChunkInfo::new("iTXt", "international textual data").multiple(),
+    ChunkInfo::new("bKGD", "background colour")
+        .after(AFTER_PALETTE)
+        .before(BEFORE_DATA),
+    ChunkInfo::new("hIST", "palette histogram")
+        .after(AFTER_PALETTE)
+        .before(BEFORE_DATA),
+    ChunkInfo::new("tRNS", "transparency")
+        .after(AFTER_PALETTE)
+        .before(BEFORE_DATA),
+    ChunkInfo::new("eXIf", "Exif metadata").before(BEFORE_DATA),
+    ChunkInfo::new("pHYs", "physical pixel dimensions").before(BEFORE_DATA),
+    ChunkInfo::new("sPLT", "suggested palette")
+        .multiple()
+        .before(BEFORE_DATA),
+    ChunkInfo::new("tIME", "last modification time"),
+    ChunkInfo::new("acTL", "APNG animation control").before(BEFORE_DATA),
+    ChunkInfo::new("fcTL", "APNG frame control").multiple(),
+    ChunkInfo::new("fdAT", "APNG frame data")
+        .multiple()
+        .after(AFTER_DATA)
+        .compressed(),
+    ChunkInfo::new("oFFs", "image offset").before(BEFORE_DATA),
+    ChunkInfo::new("pCAL", "pixel value calibration").before(BEFORE_DATA),
+    ChunkInfo::new("sCAL", "physical scale of image subject").before(BEFORE_DATA),
+    ChunkInfo::new("gIFg", "GIF graphic control extension").multiple(),
+    ChunkInfo::new("gIFx", "GIF application extension").multiple(),
+    ChunkInfo::new("gIFt", "GIF plain text extension, deprecated").multiple(),
+    ChunkInfo::new("fRAc", "fractal image parameters").multiple(),
+    ChunkInfo::new("sTER", "stereo image indicator").before(BEFORE_DATA),
+    ChunkInfo::new("dSIG", "digital signature").multiple(),
+];
+
+/// The registry entry for a chunk type, or `None` for private and unregistered types.
+pub fn lookup(chunk_type: &str) -> Option<&'static ChunkInfo> {
+    KNOWN_CHUNKS.iter().find(|info| info.name == chunk_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_lookup() {
+        let info = lookup("gAMA").unwrap();
+        assert!(!info.allows_multiple());
+        assert_eq!(
+            info.to_string(),
+            "gAMA: image gamma; must precede PLTE/IDAT"
+        );
+        assert!(lookup("ruSt").is_none());
+        assert!(lookup("fRAc").is_some());
+        assert_eq!(
+            lookup("mDCV").unwrap().to_string(),
+            "mDCV: mastering display colour volume; must precede IDAT"
+        );
+    }
+
+    #[test]
+    fn test_known_chunks_are_valid_types() {
+        for (index, info) in KNOWN_CHUNKS.iter().enumerate() {
+            let chunk_type = ChunkType::from_str(info.name()).unwrap();
+            assert_eq!(chunk_type.is_critical(), index < 4, "{}", info.name());
+            let duplicates = KNOWN_CHUNKS.iter().filter(|i| i.name == info.name).count();
+            assert_eq!(duplicates, 1, "{} is registered twice", info.name());
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use std::{fmt, fmt::Display};
+
+use crate::chunk::{Chunk, META_DATA_BYTES};
+use crate::image_data::ImageData;
+use crate::png::{Png, PngError};
+use crate::registry;
+
+pub const LARGE_ANCILLARY_BYTES: u32 = 64 * 1024;
+pub const ENTROPY_MIN_BYTES: usize = 256;
+pub const HIGH_ENTROPY_BITS: f64 = 7.5;
+pub const LSB_MIN_SAMPLES: usize = 4096;
+pub const LSB_EMBEDDING_PROBABILITY: f64 = 0.95;
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum Risk {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Finding {
+    PrivateChunk(usize, String),
+    UnknownChunk(usize, String),
+    ChunkAfterEnd(usize, String),
+    LargeAncillaryChunk(usize, String, u32),
+    HighEntropyChunk(usize, String, f64),
+    TrailingData(usize, usize),
+    LsbAnomaly(f64),
+    UndecodableImageData(String),
+}
+
+impl Finding {
+    pub fn risk(&self) -> Risk {
+        match self {
+            Self::UnknownChunk(..) => Risk::Low,
+            Self::PrivateChunk(..) | Self::LargeAncillaryChunk(..) | Self::HighEntropyChunk(..) => {
+                Risk::Medium
+            }
+            Self::ChunkAfterEnd(..)
+            | Self::TrailingData(..)
+            | Self::LsbAnomaly(_)
+            | Self::UndecodableImageData(_) => Risk::High,
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PrivateChunk(index, chunk_type) => {
+                write!(f, "chunk #{} {} is a private chunk type", index, chunk_type)
+            }
+            Self::UnknownChunk(index, chunk_type) => {
+                write!(
+                    f,
+                    "chunk #{} {} is not a registered chunk type",
+                    index, chunk_type
+                )
+            }
+            Self::ChunkAfterEnd(index, chunk_type) => {
+                write!(f, "chunk #{} {} comes after IEND", index, chunk_type)
+            }
+            Self::LargeAncillaryChunk(index, chunk_type, length) => write!(
+                f,
+                "chunk #{} {} is an unusually large ancillary chunk ({} bytes)",
+                index, chunk_type, length
+            ),
+            Self::HighEntropyChunk(index, chunk_type, entropy) => write!(
+                f,
+                "chunk #{} {} has a high entropy payload ({:.2} bits per byte)",
+                index, chunk_type, entropy
+            ),
+            Self::TrailingData(offset, length) => write!(
+                f,
+                "{} bytes appended after the PNG stream at offset {}",
+                length, offset
+            ),
+            Self::LsbAnomaly(probability) => write!(
+                f,
+                "pixel data least significant bits look embedded (chi-square probability {:.3})",
+                probability
+            ),
+            Self::UndecodableImageData(reason) => {
+                write!(f, "pixel data could not be analysed: {}", reason)
+            }
+        }
+    }
+}
+
+pub struct ScanReport {
+    findings: Vec<Finding>,
+    lsb_probability: Option<f64>,
+}
+
+impl ScanReport {
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// The chi-square probability that pixel LSBs carry embedded data, when it could be computed.
+    pub fn lsb_probability(&self) -> Option<f64> {
+        self.lsb_probability
+    }
+
+    pub fn risk(&self) -> Risk {
+        self.findings
+            .iter()
+            .map(|finding| finding.risk())
+            .max()
+            .unwrap_or(Risk::None)
+    }
+}
+
+/// Looks for places where data may be hidden in a PNG file.
+///
+/// Whatever `Png::try_from` leaves in the trailer is walked as a chunk stream too, so that
+/// chunks after IEND can be reported apart from bytes that do not parse as chunks.
+pub fn scan(bytes: &[u8]) -> Result<ScanReport, PngError> {
+    let png = Png::try_from(bytes)?;
+    let mut findings = vec![];
+
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        findings.extend(chunk_findings(index, chunk, false));
+    }
+
+    let trailer = png.trailer();
+    let trailer_start = bytes.len() - trailer.len();
+    let mut offset = 0;
+    let mut index = png.chunks().len();
+    while offset < trailer.len() {
+        match Chunk::try_from(&trailer[offset..]) {
+            Ok(chunk) => {
+                findings.extend(chunk_findings(index, &chunk, true));
+                offset += chunk.length() as usize + META_DATA_BYTES;
+                index += 1;
+            }
+            Err(_) => {
+                findings.push(Finding::TrailingData(
+                    trailer_start + offset,
+                    trailer.len() - offset,
+                ));
+                break;
+            }
+        }
+    }
+
+    let lsb_probability = match ImageData::try_from(&png) {
+        Ok(image_data) => image_data
+            .sample_low_bytes()
+            .filter(|samples| samples.len() >= LSB_MIN_SAMPLES)
+            .map(|samples| lsb_embedding_probability(&samples)),
+        Err(image_data_error) => {
+            findings.push(Finding::UndecodableImageData(image_data_error.to_string()));
+            None
+        }
+    };
+    if let Some(probability) = lsb_probability.filter(|&p| p >= LSB_EMBEDDING_PROBABILITY) {
+        findings.push(Finding::LsbAnomaly(probability));
+    }
+
+    Ok(ScanReport {
+        findings,
+        lsb_probability,
+    })
+}
+
+fn chunk_findings(index: usize, chunk: &Chunk, after_end: bool) -> Vec<Finding> {
+    let mut findings = vec![];
+    let chunk_type = chunk.chunk_type();
+    let name = chunk_type.to_string();
+
+    if after_end {
+        findings.push(Finding::ChunkAfterEnd(index, name.clone()));
+    }
+    if !chunk_type.is_public() {
+        findings.push(Finding::PrivateChunk(index, name.clone()));
+    } else if registry::lookup(&name).is_none() {
+        findings.push(Finding::UnknownChunk(index, name.clone()));
+    }
+
+    if chunk_type.is_critical() {
+        return findings;
+    }
+    if chunk.length() > LARGE_ANCILLARY_BYTES {
+        findings.push(Finding::LargeAncillaryChunk(
+            index,
+            name.clone(),
+            chunk.length(),
+        ));
+    }
+    if chunk.data().len() >= ENTROPY_MIN_BYTES && !is_compressed(chunk) {
+        let entropy = shannon_entropy(chunk.data());
+        if entropy >= HIGH_ENTROPY_BITS {
+            findings.push(Finding::HighEntropyChunk(index, name, entropy));
+        }
+    }
+
+    findings
+}
+
+/// Whether the chunk's data is deflated, from the registry or from the iTXt compression flag
+/// that follows the keyword's null separator.
+fn is_compressed(chunk: &Chunk) -> bool {
+    let name = chunk.chunk_type().to_string();
+    match name.as_str() {
+        "iTXt" => {
+            let data = chunk.data();
+            data.iter()
+                .position(|&byte| byte == 0)
+                .and_then(|separator| data.get(separator + 1))
+                .is_some_and(|&flag| flag != 0)
+        }
+        _ => registry::lookup(&name).is_some_and(|info| info.is_compressed()),
+    }
+}
+
+/// Entropy of the byte distribution in bits per byte, from 0 to 8.
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    let mut histogram = [0usize; 256];
+    for &byte in data {
+        histogram[byte as usize] += 1;
+    }
+
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / data.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Westfeld and Pfitzmann's chi-square attack on sample values.
+///
+/// Replacing LSBs with message bits evens out the counts of each pair of values that
+/// differ only in their LSB. The result is the probability that the pairs are that even
+/// because of embedding, close to 1 for a fully embedded image and close to 0 otherwise.
+pub fn lsb_embedding_probability(samples: &[u8]) -> f64 {
+    let mut histogram = [0usize; 256];
+    for &sample in samples {
+        histogram[sample as usize] += 1;
+    }
+
+    let mut chi_square = 0.0;
+    let mut categories = 0;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected < 1.0 {
+            continue;
+        }
+        chi_square += (pair[0] as f64 - expected).powi(2) / expected;
+        categories += 1;
+    }
+
+    if categories < 2 {
+        return 0.0;
+    }
+    1.0 - chi_square_cdf(chi_square, (categories - 1) as f64)
+}
+
+/// Wilson-Hilferty approximation of the chi-square distribution function.
+fn chi_square_cdf(x: f64, degrees_of_freedom: f64) -> f64 {
+    let k = degrees_of_freedom;
+    let variance = 2.0 / (9.0 * k);
+    let z = ((x / k).cbrt() - (1.0 - variance)) / variance.sqrt();
+    normal_cdf(z)
+}
+
+/// Abramowitz and Stegun 7.1.26 approximation of the standard normal distribution function.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - polynomial * (-x * x).exp();
+
+    match z >= 0.0 {
+        true => 0.5 * (1.0 + erf),
+        false => 0.5 * (1.0 - erf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::chunk;
+
+    fn noise(length: usize) -> Vec<u8> {
+        let mut state: u32 = 12345;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn png_bytes(chunks: &[Chunk]) -> Vec<u8> {
+        Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(chunks.iter().flat_map(|chunk| chunk.as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_finds_hidden_chunks() {
+        let mut bytes = png_bytes(&[
+            chunk("IHDR", vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            chunk("ruSt", b"secret"),
+            chunk("hIDE", noise(1024)),
+            chunk("IEND", vec![]),
+            chunk("tEXt", b"Comment\0after the end"),
+        ]);
+        bytes.extend_from_slice(b"PK\x03\x04 appended zip");
+
+        let report = scan(&bytes).unwrap();
+        let findings = report.findings();
+
+        assert!(matches!(&findings[0], Finding::PrivateChunk(1, t) if t == "ruSt"));
+        assert!(matches!(&findings[1], Finding::UnknownChunk(2, t) if t == "hIDE"));
+        assert!(matches!(&findings[2], Finding::HighEntropyChunk(2, ..)));
+        assert!(matches!(&findings[3], Finding::ChunkAfterEnd(4, t) if t == "tEXt"));
+        assert!(matches!(&findings[4], Finding::TrailingData(_, 17)));
+        assert_eq!(report.risk(), Risk::High);
+    }
+
+    #[test]
+    fn test_entropy_of_itxt_depends_on_compression_flag() {
+        let itxt = |flag: u8| {
+            let mut data = b"Comment\0".to_vec();
+            data.extend([flag, 0, 0, 0]);
+            data.extend(noise(1024));
+            chunk("iTXt", data)
+        };
+
+        assert!(matches!(
+            chunk_findings(1, &itxt(0), false)[..],
+            [Finding::HighEntropyChunk(1, ..)]
+        ));
+        assert!(chunk_findings(1, &itxt(1), false).is_empty());
+    }
+
+    #[test]
+    fn test_broken_image_data_is_high_risk() {
+        let bytes = png_bytes(&[chunk("IDAT", vec![]), chunk("IEND", vec![])]);
+        let report = scan(&bytes).unwrap();
+        assert!(matches!(
+            report.findings(),
+            [Finding::UndecodableImageData(_)]
+        ));
+        assert_eq!(report.risk(), Risk::High);
+    }
+
+    #[test]
+    fn test_scan_rejects_non_png() {
+        assert!(matches!(
+            scan(b"GIF89a\0\0\0\0"),
+            Err(PngError::PngHeaderIncorrect)
+        ));
+    }
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert_eq!(shannon_entropy(&[7; 100]), 0.0);
+        assert!(shannon_entropy(&noise(4096)) > 7.9);
+    }
+
+    #[test]
+    fn test_lsb_embedding_probability() {
+        let cover: Vec<u8> = (0..8192).map(|i| ((i % 64) * 4) as u8).collect();
+        assert!(lsb_embedding_probability(&cover) < 0.05);
+
+        let stego: Vec<u8> = cover
+            .iter()
+            .zip(noise(8192))
+            .map(|(sample, bit)| sample | (bit >> 7))
+            .collect();
+        assert!(lsb_embedding_probability(&stego) > LSB_EMBEDDING_PROBABILITY);
+    }
+}
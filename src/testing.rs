@@ -0,0 +1,21 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::ihdr::Ihdr;
+
+/// A chunk of `chunk_type`, which must be valid, holding `data`.
+pub fn chunk(chunk_type: &str, data: impl AsRef<[u8]>) -> Chunk {
+    Chunk::new(
+        ChunkType::from_str(chunk_type).unwrap(),
+        data.as_ref().to_vec(),
+    )
+}
+
+/// The header of a 100 by 10 image with the given bit depth and colour type.
+pub fn ihdr(bit_depth: u8, color_type: u8) -> Ihdr {
+    let data = [0, 0, 0, 100, 0, 0, 0, 10, bit_depth, color_type, 0, 0, 0];
+    Ihdr::try_from(&chunk(Ihdr::CHUNK_TYPE, data)).unwrap()
+}
@@ -0,0 +1,222 @@
+use std::{fmt, fmt::Display};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image_data::inflate;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+pub const MAX_KEYWORD_LENGTH: usize = 79;
+
+/// The keyword and text of a tEXt, zTXt or iTXt chunk.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TextChunk {
+    keyword: String,
+    text: String,
+    language: String,
+    translated_keyword: String,
+}
+
+impl TextChunk {
+    pub fn new(keyword: &str, text: &str) -> TextChunk {
+        TextChunk {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+            language: String::new(),
+            translated_keyword: String::new(),
+        }
+    }
+
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The same keyword, language and translated keyword with different text.
+    pub fn with_text(&self, text: &str) -> TextChunk {
+        TextChunk {
+            text: text.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Encodes the text as a chunk of the given text type. iTXt text is stored uncompressed,
+    /// while tEXt and zTXt fail for text outside ISO 8859-1.
+    pub fn to_chunk(&self, chunk_type: &ChunkType) -> Result<Chunk, TextError> {
+        let mut data = to_latin1(&self.keyword)?;
+        data.push(0);
+
+        match chunk_type.to_string().as_str() {
+            "tEXt" => data.extend(to_latin1(&self.text)?),
+            "zTXt" => {
+                data.push(0);
+                data.extend(compress_to_vec_zlib(&to_latin1(&self.text)?, 6));
+            }
+            "iTXt" => {
+                data.extend([0, 0]);
+                data.extend(self.language.as_bytes());
+                data.push(0);
+                data.extend(self.translated_keyword.as_bytes());
+                data.push(0);
+                data.extend(self.text.as_bytes());
+            }
+            other => return Err(TextError::NotText(other.to_string())),
+        }
+
+        Ok(Chunk::new(chunk_type.clone(), data))
+    }
+}
+
+impl TryFrom<&Chunk> for TextChunk {
+    type Error = TextError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        let chunk_type = chunk.chunk_type().to_string();
+        let (keyword, rest) = split_null(chunk.data()).ok_or(TextError::MissingSeparator)?;
+        let keyword = latin1(keyword);
+
+        let (mut language, mut translated_keyword) = (String::new(), String::new());
+        let text = match chunk_type.as_str() {
+            "tEXt" => latin1(rest),
+            "zTXt" => {
+                let (method, compressed) = rest.split_first().ok_or(TextError::Truncated)?;
+                latin1(&decompress(*method, compressed)?)
+            }
+            "iTXt" => {
+                let [flag, method, rest @ ..] = rest else {
+                    return Err(TextError::Truncated);
+                };
+                let (language_tag, rest) = split_null(rest).ok_or(TextError::MissingSeparator)?;
+                let (translated, text) = split_null(rest).ok_or(TextError::MissingSeparator)?;
+                language = latin1(language_tag);
+                translated_keyword =
+                    String::from_utf8(translated.to_vec()).map_err(|_| TextError::InvalidUtf8)?;
+                let text = match flag {
+                    0 => text.to_vec(),
+                    _ => decompress(*method, text)?,
+                };
+                String::from_utf8(text).map_err(|_| TextError::InvalidUtf8)?
+            }
+            _ => return Err(TextError::NotText(chunk_type)),
+        };
+
+        Ok(TextChunk {
+            keyword,
+            text,
+            language,
+            translated_keyword,
+        })
+    }
+}
+
+impl Display for TextChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}", self.keyword, self.text)
+    }
+}
+
+fn split_null(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let position = data.iter().position(|&byte| byte == 0)?;
+    Some((&data[..position], &data[position + 1..]))
+}
+
+/// tEXt and zTXt are ISO 8859-1, whose code points map directly to chars.
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+fn to_latin1(text: &str) -> Result<Vec<u8>, TextError> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).map_err(|_| TextError::NotLatin1(c)))
+        .collect()
+}
+
+fn decompress(method: u8, data: &[u8]) -> Result<Vec<u8>, TextError> {
+    if method != 0 {
+        return Err(TextError::UnknownCompression(method));
+    }
+    inflate(data).map_err(|e| TextError::Inflate(e.to_string()))
+}
+
+#[derive(Debug)]
+pub enum TextError {
+    NotText(String),
+    MissingSeparator,
+    Truncated,
+    UnknownCompression(u8),
+    Inflate(String),
+    InvalidUtf8,
+    NotLatin1(char),
+}
+
+impl Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotText(chunk_type) => write!(f, "Error: {} is not a text chunk", chunk_type),
+            Self::MissingSeparator => write!(f, "Error: text chunk is missing a null separator"),
+            Self::Truncated => write!(f, "Error: text chunk is truncated"),
+            Self::UnknownCompression(method) => {
+                write!(f, "Error: unknown text compression method {}", method)
+            }
+            Self::Inflate(inflate_error) => write!(f, "Error: {}", inflate_error),
+            Self::InvalidUtf8 => write!(f, "Error: iTXt text is not valid UTF-8"),
+            Self::NotLatin1(c) => write!(f, "Error: {:?} cannot be stored outside iTXt", c),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::chunk;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_text_chunk() {
+        let text = TextChunk::try_from(&chunk("tEXt", b"Comment\0caf\xe9")).unwrap();
+        assert_eq!(text.keyword(), "Comment");
+        assert_eq!(text.text(), "café");
+    }
+
+    #[test]
+    fn test_compressed_text_chunk() {
+        let mut data = b"Title\0\0".to_vec();
+        data.extend(compress_to_vec_zlib(b"Dice", 6));
+        let text = TextChunk::try_from(&chunk("zTXt", data)).unwrap();
+        assert_eq!(text.text(), "Dice");
+    }
+
+    #[test]
+    fn test_international_text_chunk() {
+        let data = "Author\0\0\0fr\0Auteur\0Léa".as_bytes().to_vec();
+        let text = TextChunk::try_from(&chunk("iTXt", data)).unwrap();
+        assert_eq!(text.keyword(), "Author");
+        assert_eq!(text.text(), "Léa");
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        for chunk_type in ["tEXt", "zTXt", "iTXt"] {
+            let chunk_type = ChunkType::from_str(chunk_type).unwrap();
+            let chunk = TextChunk::new("Comment", "café")
+                .to_chunk(&chunk_type)
+                .unwrap();
+            let text = TextChunk::try_from(&chunk).unwrap();
+            assert_eq!(text, TextChunk::new("Comment", "café"));
+        }
+
+        let text_type = ChunkType::from_str("tEXt").unwrap();
+        assert!(TextChunk::new("Comment", "日本")
+            .to_chunk(&text_type)
+            .is_err());
+    }
+
+    #[test]
+    fn test_not_text_chunk() {
+        assert!(TextChunk::try_from(&chunk("ruSt", b"a\0b")).is_err());
+    }
+}